@@ -0,0 +1,1025 @@
+use std::{collections::HashMap, error::Error, fmt, fs, io, ops::Range, path::Path};
+
+use crate::opcode::Opcode;
+
+/// Reasons a line of assembly could not be assembled.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AsmError {
+    /// the mnemonic isn't a CHIP-8 instruction
+    UnknownMnemonic { mnemonic: String },
+    /// the operands don't match any form of the instruction
+    BadOperands { line: String },
+    /// a number doesn't fit in the field it's encoded into
+    OutOfRange { value: u16, max: u16 },
+    /// the name isn't one of the registers V0 to VF
+    InvalidRegister { name: String },
+    /// an operand isn't a register, number, label, or any of I, [I], DT, ST, K, F or B
+    InvalidOperand { operand: String },
+    /// a label is used without being defined
+    UndefinedLabel { label: String },
+    /// a label is defined more than once
+    DuplicateLabel { label: String },
+    /// a label's name isn't an identifier (letters, digits and '_', not starting with a digit)
+    InvalidLabel { label: String },
+    /// an ORG directive moves the origin back over code that's already been emitted
+    OriginBehind { origin: u16, addr: u16 },
+    /// a row of a SPRITE isn't 8 pixels of '.' (unlit) or '#' (lit)
+    InvalidSprite { row: String },
+    /// an error in a program, with where it is: its (1-based) line and column, and the text
+    /// of that line
+    AtLine {
+        line: usize,
+        column: usize,
+        text: String,
+        error: Box<AsmError>,
+    },
+}
+
+impl AsmError {
+    /// the error on a line of a program, at the token that caused it (if it can be found)
+    fn at_line(self, line: usize, text: &str) -> AsmError {
+        let column = match self.token() {
+            // (uppercasing ASCII keeps byte offsets, so this finds mnemonics in any case)
+            Some(token) if !token.is_empty() => {
+                text.to_ascii_uppercase().find(&token.to_ascii_uppercase())
+            }
+            _ => None,
+        }
+        .or_else(|| text.find(|c: char| !c.is_whitespace()))
+        .map_or(1, |idx| text[..idx].chars().count() + 1);
+
+        AsmError::AtLine {
+            line,
+            column,
+            text: text.to_string(),
+            error: Box::new(self),
+        }
+    }
+
+    /// the text in the source that caused the error, if it names one
+    fn token(&self) -> Option<&str> {
+        match self {
+            AsmError::UnknownMnemonic { mnemonic } => Some(mnemonic),
+            AsmError::InvalidRegister { name } => Some(name),
+            AsmError::InvalidOperand { operand } => Some(operand),
+            AsmError::UndefinedLabel { label }
+            | AsmError::DuplicateLabel { label }
+            | AsmError::InvalidLabel { label } => Some(label),
+            AsmError::InvalidSprite { row } => Some(row),
+            _ => None,
+        }
+    }
+
+    /// the line of the program the error is on, if it's known
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            AsmError::AtLine { line, .. } => Some(*line),
+            _ => None,
+        }
+    }
+
+    /// the error itself, without where it is
+    pub fn kind(&self) -> &AsmError {
+        match self {
+            AsmError::AtLine { error, .. } => error,
+            error => error,
+        }
+    }
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AsmError::UnknownMnemonic { mnemonic } => {
+                write!(f, "Unknown instruction '{}'", mnemonic)
+            }
+            AsmError::BadOperands { line } => {
+                write!(f, "Invalid operands in '{}'", line)?;
+                let (mnemonic, _) = split_mnemonic(line);
+                let forms: Vec<String> = FORMS
+                    .iter()
+                    .filter(|form| split_mnemonic(form).0 == mnemonic)
+                    .map(|form| format!("'{}'", form))
+                    .collect();
+                match forms.is_empty() {
+                    true => Ok(()),
+                    false => write!(f, ": expected {}", forms.join(" or ")),
+                }
+            }
+            AsmError::OutOfRange { value, max } => {
+                write!(
+                    f,
+                    "{:#x} is out of range: must be at most {:#x}",
+                    value, max
+                )
+            }
+            AsmError::InvalidRegister { name } => {
+                write!(f, "'{}' is not a register: must be V0 to VF", name)
+            }
+            AsmError::InvalidOperand { operand } => {
+                write!(f, "Cannot parse operand '{}'", operand)
+            }
+            AsmError::UndefinedLabel { label } => write!(f, "Undefined label '{}'", label),
+            AsmError::DuplicateLabel { label } => {
+                write!(f, "Label '{}' is already defined", label)
+            }
+            AsmError::InvalidLabel { label } => {
+                write!(f, "'{}' is not a valid label name", label)
+            }
+            AsmError::OriginBehind { origin, addr } => write!(
+                f,
+                "Cannot set the origin to {:#05x}: code has already been emitted up to {:#05x}",
+                origin, addr
+            ),
+            AsmError::InvalidSprite { row } => write!(
+                f,
+                "Invalid sprite row '{}': must be 8 pixels of '.' or '#'",
+                row
+            ),
+            AsmError::AtLine {
+                line,
+                column,
+                text,
+                error,
+            } => {
+                let gutter = " ".repeat(line.to_string().len());
+                let width = error
+                    .token()
+                    .map_or(1, |token| token.chars().count().max(1));
+                writeln!(f, "error: {}", error)?;
+                writeln!(f, "{}--> line {}, column {}", gutter, line, column)?;
+                writeln!(f, "{} |", gutter)?;
+                writeln!(f, "{} | {}", line, text)?;
+                write!(
+                    f,
+                    "{} | {}{}",
+                    gutter,
+                    " ".repeat(column - 1),
+                    "^".repeat(width)
+                )
+            }
+        }
+    }
+}
+
+impl Error for AsmError {}
+
+/// every mnemonic `assemble_line` understands
+const MNEMONICS: [&str; 21] = [
+    "HALT", "CLS", "RET", "SYS", "JP", "CALL", "SE", "SNE", "LD", "ADD", "OR", "AND", "XOR", "SUB",
+    "SHR", "SUBN", "SHL", "RND", "DRW", "SKP", "SKNP",
+];
+
+/// every form of every instruction and directive, as described by a `BadOperands` error
+/// (x and y are registers, nnn an address, nnnn a word, kk a byte and n a nibble)
+const FORMS: [&str; 41] = [
+    "SYS nnn",
+    "JP nnn",
+    "JP V0, nnn",
+    "CALL nnn",
+    "SE Vx, kk",
+    "SE Vx, Vy",
+    "SNE Vx, kk",
+    "SNE Vx, Vy",
+    "LD Vx, kk",
+    "LD Vx, Vy",
+    "LD I, nnn",
+    "LD Vx, DT",
+    "LD Vx, K",
+    "LD DT, Vx",
+    "LD ST, Vx",
+    "LD F, Vx",
+    "LD B, Vx",
+    "LD [I], Vx",
+    "LD Vx, [I]",
+    "ADD Vx, kk",
+    "ADD Vx, Vy",
+    "ADD I, Vx",
+    "OR Vx, Vy",
+    "AND Vx, Vy",
+    "XOR Vx, Vy",
+    "SUB Vx, Vy",
+    "SHR Vx",
+    "SHR Vx, Vy",
+    "SUBN Vx, Vy",
+    "SHL Vx",
+    "SHL Vx, Vy",
+    "RND Vx, kk",
+    "DRW Vx, Vy, n",
+    "SKP Vx",
+    "SKNP Vx",
+    "HALT",
+    "CLS",
+    "RET",
+    "ORG nnn",
+    "DB kk, ...",
+    "DW nnnn, ...",
+];
+
+/// The operands an instruction can take.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Operand {
+    /// a register, V0 to VF
+    V(u8),
+    /// an address or immediate value
+    Num(u16),
+    /// the index register
+    I,
+    /// the memory referenced by the index register
+    AtI,
+    DT,
+    ST,
+    K,
+    F,
+    B,
+}
+
+impl Operand {
+    /// parse an operand, replacing any label with the address it's defined at in `labels`
+    fn parse(token: &str, labels: &HashMap<String, u16>) -> Result<Operand, AsmError> {
+        let invalid = || AsmError::InvalidOperand {
+            operand: token.to_string(),
+        };
+        let upper = token.to_ascii_uppercase();
+        let operand = match upper.as_str() {
+            "I" => Operand::I,
+            "[I]" => Operand::AtI,
+            "DT" => Operand::DT,
+            "ST" => Operand::ST,
+            "K" => Operand::K,
+            "F" => Operand::F,
+            "B" => Operand::B,
+            // (so a label may start with a V, as long as it's longer than a register name)
+            reg if reg.starts_with('V') && reg.len() == 2 => Operand::V(parse_register(token)?),
+            _ if is_identifier(token) => match labels.get(token) {
+                Some(addr) => Operand::Num(*addr),
+                None => {
+                    return Err(AsmError::UndefinedLabel {
+                        label: token.to_string(),
+                    });
+                }
+            },
+            num => Operand::Num(parse_number(num).ok_or_else(invalid)?),
+        };
+        Ok(operand)
+    }
+}
+
+/// parse a number, in decimal or with a "0x" (hex) or "0b" (binary) prefix
+fn parse_number(token: &str) -> Option<u16> {
+    let upper = token.to_ascii_uppercase();
+    let value = if let Some(hex) = upper.strip_prefix("0X") {
+        u16::from_str_radix(hex, 16)
+    } else if let Some(bin) = upper.strip_prefix("0B") {
+        u16::from_str_radix(bin, 2)
+    } else {
+        upper.parse()
+    };
+    value.ok()
+}
+
+/// whether a token can name a label: letters, digits and '_', not starting with a digit
+fn is_identifier(token: &str) -> bool {
+    let mut chars = token.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Parse a register name, V0 to VF (case-insensitive), into its index (0 to 15).
+pub fn parse_register(name: &str) -> Result<u8, AsmError> {
+    let invalid = || AsmError::InvalidRegister {
+        name: name.to_string(),
+    };
+    let digit = name
+        .strip_prefix(['V', 'v'])
+        .filter(|digit| digit.len() == 1)
+        .ok_or_else(invalid)?;
+    u8::from_str_radix(digit, 16).map_err(|_| invalid())
+}
+
+/// check a number fits in a field of at most `max`
+fn field(value: u16, max: u16) -> Result<u16, AsmError> {
+    match value <= max {
+        true => Ok(value),
+        false => Err(AsmError::OutOfRange { value, max }),
+    }
+}
+
+/// Assemble a single line of CHIP-8 assembly (in the syntax `disassemble` produces)
+/// into its opcode, e.g. "ADD V0, V1" -> 0x8014.
+///
+/// Mnemonics and register names are case-insensitive; anything after a ';' is a comment.
+pub fn assemble_line(line: &str) -> Result<u16, AsmError> {
+    parse_line(line).map(u16::from)
+}
+
+/// Parse a single line of CHIP-8 assembly (see `assemble_line`) into the instruction it
+/// encodes, e.g. "JP 0x200" -> `Opcode::Jump { nnn: 0x200 }`.
+pub fn parse_line(line: &str) -> Result<Opcode, AsmError> {
+    instruction(strip_comment(line), &HashMap::new())
+}
+
+/// the code on a line, without any comment or surrounding whitespace
+fn strip_comment(line: &str) -> &str {
+    line.split(';').next().unwrap_or_default().trim()
+}
+
+/// split a line of code into its (uppercased) mnemonic and the text of its operands
+fn split_mnemonic(code: &str) -> (String, &str) {
+    let (mnemonic, rest) = code.split_once(char::is_whitespace).unwrap_or((code, ""));
+    (mnemonic.to_ascii_uppercase(), rest.trim())
+}
+
+/// encode an instruction (without a comment) into its opcode, resolving any labels it uses
+fn encode(code: &str, labels: &HashMap<String, u16>) -> Result<u16, AsmError> {
+    instruction(code, labels).map(u16::from)
+}
+
+/// parse an instruction (without a comment), resolving any labels it uses
+fn instruction(code: &str, labels: &HashMap<String, u16>) -> Result<Opcode, AsmError> {
+    use Opcode::*;
+    use Operand::{AtI, B, DT, F, I, K, Num, ST, V};
+
+    let (mnemonic, rest) = split_mnemonic(code);
+    let operands = rest
+        .split(',')
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+        .map(|token| Operand::parse(token, labels))
+        .collect::<Result<Vec<Operand>, AsmError>>()?;
+
+    let byte = |kk: u16| field(kk, 0xFF).map(|kk| kk as u8);
+    let addr = |nnn: u16| field(nnn, 0xFFF);
+
+    let opcode = match (mnemonic.as_str(), &operands[..]) {
+        ("HALT", []) => Halt,
+        ("CLS", []) => Clear,
+        ("RET", []) => Return,
+        ("SYS", [Num(nnn)]) => Sys { nnn: addr(*nnn)? },
+        ("JP", [Num(nnn)]) => Jump { nnn: addr(*nnn)? },
+        ("JP", [V(0), Num(nnn)]) => JumpOffset { nnn: addr(*nnn)? },
+        ("CALL", [Num(nnn)]) => Call { nnn: addr(*nnn)? },
+        ("SE", [V(x), Num(kk)]) => SkipEqImmediate {
+            x: *x,
+            kk: byte(*kk)?,
+        },
+        ("SNE", [V(x), Num(kk)]) => SkipNeImmediate {
+            x: *x,
+            kk: byte(*kk)?,
+        },
+        ("SE", [V(x), V(y)]) => SkipEqRegister { x: *x, y: *y },
+        ("LD", [V(x), Num(kk)]) => LoadImmediate {
+            x: *x,
+            kk: byte(*kk)?,
+        },
+        ("ADD", [V(x), Num(kk)]) => AddImmediate {
+            x: *x,
+            kk: byte(*kk)?,
+        },
+        ("LD", [V(x), V(y)]) => AluLoad { x: *x, y: *y },
+        ("OR", [V(x), V(y)]) => AluOr { x: *x, y: *y },
+        ("AND", [V(x), V(y)]) => AluAnd { x: *x, y: *y },
+        ("XOR", [V(x), V(y)]) => AluXor { x: *x, y: *y },
+        ("ADD", [V(x), V(y)]) => AluAdd { x: *x, y: *y },
+        ("SUB", [V(x), V(y)]) => AluSub { x: *x, y: *y },
+        ("SHR", [V(x), V(y)]) => AluShr { x: *x, y: *y },
+        ("SHR", [V(x)]) => AluShr { x: *x, y: 0 },
+        ("SUBN", [V(x), V(y)]) => AluSubn { x: *x, y: *y },
+        ("SHL", [V(x), V(y)]) => AluShl { x: *x, y: *y },
+        ("SHL", [V(x)]) => AluShl { x: *x, y: 0 },
+        ("SNE", [V(x), V(y)]) => SkipNeRegister { x: *x, y: *y },
+        ("LD", [I, Num(nnn)]) => LoadIndex { nnn: addr(*nnn)? },
+        ("RND", [V(x), Num(kk)]) => Random {
+            x: *x,
+            kk: byte(*kk)?,
+        },
+        ("DRW", [V(x), V(y), Num(n)]) => Draw {
+            x: *x,
+            y: *y,
+            n: field(*n, 0xF)? as u8,
+        },
+        ("SKP", [V(x)]) => SkipKey { x: *x },
+        ("SKNP", [V(x)]) => SkipNotKey { x: *x },
+        ("LD", [V(x), DT]) => LoadDelay { x: *x },
+        ("LD", [V(x), K]) => WaitKey { x: *x },
+        ("LD", [DT, V(x)]) => SetDelay { x: *x },
+        ("LD", [ST, V(x)]) => SetSound { x: *x },
+        ("ADD", [I, V(x)]) => AddIndex { x: *x },
+        ("LD", [F, V(x)]) => LoadFont { x: *x },
+        ("LD", [B, V(x)]) => StoreBcd { x: *x },
+        ("LD", [AtI, V(x)]) => StoreRegisters { x: *x },
+        ("LD", [V(x), AtI]) => LoadRegisters { x: *x },
+        (mnemonic, _) if MNEMONICS.contains(&mnemonic) => {
+            return Err(AsmError::BadOperands {
+                line: code.to_string(),
+            });
+        }
+        (mnemonic, _) => {
+            return Err(AsmError::UnknownMnemonic {
+                mnemonic: mnemonic.to_string(),
+            });
+        }
+    };
+    Ok(opcode)
+}
+
+/// where programs are assembled to unless an ORG says otherwise: the start of program memory
+/// (see `CPU::program_start`)
+pub const DEFAULT_ORIGIN: u16 = 0x100;
+
+/// A program assembled from source, as a contiguous image of memory.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Assembly {
+    origin: u16,
+    bytes: Vec<u8>,
+    labels: HashMap<String, u16>,
+    /// for each line of source, its address and the range of `bytes` it emitted
+    lines: Vec<(u16, Range<usize>)>,
+}
+
+impl Assembly {
+    /// the address the first byte is to be loaded at
+    pub fn origin(&self) -> u16 {
+        self.origin
+    }
+
+    /// the assembled bytes, to be loaded from the origin onwards
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// the address each label resolved to
+    pub fn symbols(&self) -> HashMap<String, u16> {
+        self.labels.clone()
+    }
+
+    /// the symbol table as text, one "<address> <label>" line per label in address order
+    /// (e.g. "0x104 loop")
+    pub fn to_sym(&self) -> String {
+        let mut symbols: Vec<(&String, &u16)> = self.labels.iter().collect();
+        symbols.sort_by_key(|&(label, addr)| (addr, label));
+        symbols
+            .into_iter()
+            .map(|(label, addr)| format!("{:#05x} {}\n", addr, label))
+            .collect()
+    }
+
+    /// write the symbol table to a file (conventionally with a .sym extension)
+    pub fn write_sym(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.to_sym())
+    }
+
+    /// an assembly with nothing emitted yet, at the default origin
+    fn empty(labels: HashMap<String, u16>) -> Assembly {
+        Assembly {
+            origin: DEFAULT_ORIGIN,
+            bytes: vec![],
+            labels,
+            lines: vec![],
+        }
+    }
+
+    /// the address the next byte will be emitted at
+    fn addr(&self) -> u16 {
+        self.origin + self.bytes.len() as u16
+    }
+
+    /// the first pass over a line: define its label, and reserve space for what it emits
+    fn lay_out(&mut self, line: &str) -> Result<(), AsmError> {
+        let (label, code) = split_label(strip_comment(line))?;
+        if let Some(label) = label {
+            let addr = self.addr();
+            if self.labels.insert(label.to_string(), addr).is_some() {
+                return Err(AsmError::DuplicateLabel {
+                    label: label.to_string(),
+                });
+            }
+        }
+        match split_mnemonic(code) {
+            (directive, operand) if directive == "ORG" => self.org(code, operand)?,
+            _ => {
+                let len = self.bytes.len() + size_of(code);
+                self.bytes.resize(len, 0);
+            }
+        }
+        Ok(())
+    }
+
+    /// the second pass over a line: emit it, now that every label is known
+    fn emit(&mut self, line: &str) -> Result<(), AsmError> {
+        let (_, code) = split_label(strip_comment(line))?;
+        let mut start = self.bytes.len();
+
+        match split_mnemonic(code) {
+            _ if code.is_empty() => {}
+            (directive, operand) if directive == "ORG" => {
+                self.org(code, operand)?;
+                // (the padding belongs to no line: the ORG is listed at the address it moves to)
+                start = self.bytes.len();
+            }
+            (directive, operands) if directive == "DB" => {
+                for byte in data(code, operands, &self.labels, 0xFF)? {
+                    self.bytes.push(byte as u8);
+                }
+            }
+            (directive, operands) if directive == "DW" => {
+                for word in data(code, operands, &self.labels, 0xFFFF)? {
+                    self.bytes.extend(word.to_be_bytes());
+                }
+            }
+            (directive, rows) if directive == "SPRITE" => {
+                for row in rows.split(',') {
+                    self.bytes.push(sprite_row(row.trim())?);
+                }
+            }
+            _ => {
+                let opcode = encode(code, &self.labels)?;
+                self.bytes.extend(opcode.to_be_bytes());
+            }
+        }
+
+        let addr = self.origin + start as u16;
+        self.lines.push((addr, start..self.bytes.len()));
+        Ok(())
+    }
+
+    /// apply an ORG directive (`code`), with the given operand
+    fn org(&mut self, code: &str, operand: &str) -> Result<(), AsmError> {
+        let origin = match Operand::parse(operand, &self.labels)? {
+            Operand::Num(origin) => field(origin, 0xFFF)?,
+            _ => {
+                return Err(AsmError::BadOperands {
+                    line: code.to_string(),
+                });
+            }
+        };
+        let addr = self.addr();
+        if self.bytes.is_empty() {
+            self.origin = origin;
+        } else if origin < addr {
+            return Err(AsmError::OriginBehind { origin, addr });
+        } else {
+            self.bytes.resize((origin - self.origin) as usize, 0);
+        }
+        Ok(())
+    }
+}
+
+/// Assemble a program, one instruction or directive per line.
+///
+/// Any line may start with a label ("loop: ADD V0, 1", or "loop:" on its own) naming its
+/// address, which instructions anywhere in the program can use in place of a number
+/// (e.g. "JP loop").
+/// The `ORG <addr>` directive moves on to assembling at `addr`, padding the gap with zeroes;
+/// before anything is emitted, it sets the origin of the program instead (by default, 0x100).
+/// As the layout depends on it, its address can only use labels defined above it.
+/// `DB` and `DW` emit their comma-separated operands as raw bytes or (big-endian) words,
+/// e.g. for sprites to be drawn with "LD I, sprite" and DRW. `SPRITE` emits rows of a sprite
+/// drawn as 8 pixels of '.' or '#' instead, so ".####..." emits 0b01111000.
+pub fn assemble(source: &str) -> Result<Assembly, AsmError> {
+    // the first pass lays the program out (as zeroes) to find the address of every label...
+    let mut layout = Assembly::empty(HashMap::new());
+    for (idx, line) in source.lines().enumerate() {
+        layout
+            .lay_out(line)
+            .map_err(|err| err.at_line(idx + 1, line))?;
+    }
+
+    // ...so the second can resolve references to labels defined after them
+    let mut assembly = Assembly::empty(layout.labels);
+    for (idx, line) in source.lines().enumerate() {
+        assembly
+            .emit(line)
+            .map_err(|err| err.at_line(idx + 1, line))?;
+    }
+    Ok(assembly)
+}
+
+/// Assemble a program (see `assemble`) into a listing of each line of its source, alongside
+/// the address it was assembled at and the bytes it emitted, e.g.
+/// "0x100  80 14  ADD V0, V1 ; add the registers".
+pub fn assemble_listing(source: &str) -> Result<String, AsmError> {
+    let assembly = assemble(source)?;
+    let listing = source
+        .lines()
+        .zip(&assembly.lines)
+        .map(|(line, (addr, bytes))| {
+            let hex: Vec<String> = assembly.bytes[bytes.clone()]
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect();
+            let row = format!("{:#05x}  {:<5}  {}", addr, hex.join(" "), line.trim());
+            row.trim_end().to_string() + "\n"
+        });
+    Ok(listing.collect())
+}
+
+/// split a line of code (without a comment) into its label, if it has one, and the rest
+fn split_label(code: &str) -> Result<(Option<&str>, &str), AsmError> {
+    let Some((label, rest)) = code.split_once(':') else {
+        return Ok((None, code));
+    };
+    let label = label.trim();
+    match is_identifier(label) {
+        true => Ok((Some(label), rest.trim())),
+        false => Err(AsmError::InvalidLabel {
+            label: label.to_string(),
+        }),
+    }
+}
+
+/// how many bytes a line of code (other than an ORG) emits, without resolving its operands
+fn size_of(code: &str) -> usize {
+    if code.is_empty() {
+        return 0;
+    }
+    match split_mnemonic(code) {
+        (directive, operands) if directive == "DB" || directive == "SPRITE" => {
+            operands.split(',').count()
+        }
+        (directive, operands) if directive == "DW" => 2 * operands.split(',').count(),
+        _ => 2,
+    }
+}
+
+/// parse the operands of a data directive, each a number (or label) of at most `max`
+fn data(
+    code: &str,
+    operands: &str,
+    labels: &HashMap<String, u16>,
+    max: u16,
+) -> Result<Vec<u16>, AsmError> {
+    let bad_operands = || AsmError::BadOperands {
+        line: code.to_string(),
+    };
+    if operands.is_empty() {
+        return Err(bad_operands());
+    }
+    operands
+        .split(',')
+        .map(|token| match Operand::parse(token.trim(), labels)? {
+            Operand::Num(value) => field(value, max),
+            _ => Err(bad_operands()),
+        })
+        .collect()
+}
+
+/// pack a row of a sprite, drawn as 8 pixels of '.' or '#', into a byte
+fn sprite_row(row: &str) -> Result<u8, AsmError> {
+    let invalid = || AsmError::InvalidSprite {
+        row: row.to_string(),
+    };
+    if row.chars().count() != 8 {
+        return Err(invalid());
+    }
+    row.chars().try_fold(0, |byte, pixel| match pixel {
+        '.' => Ok(byte << 1),
+        '#' => Ok(byte << 1 | 1),
+        _ => Err(invalid()),
+    })
+}
+
+#[test]
+pub fn test_assemble_line() {
+    assert_eq!(assemble_line("ADD V0, V1"), Ok(0x8014));
+    assert_eq!(assemble_line("RET"), Ok(0x00EE));
+    assert_eq!(assemble_line("CLS"), Ok(0x00E0));
+    assert_eq!(assemble_line("JP 0x200"), Ok(0x1200));
+    assert_eq!(assemble_line("JP V0, 0x200"), Ok(0xB200));
+    assert_eq!(assemble_line("add v3, 238"), Ok(0x73EE));
+    assert_eq!(assemble_line("LD I, 0x2F0 ; the sprite"), Ok(0xA2F0));
+    assert_eq!(assemble_line("DRW V0, V1, 5"), Ok(0xD015));
+    assert_eq!(assemble_line("LD B, VA"), Ok(0xFA33));
+    assert_eq!(assemble_line("LD V5, [I]"), Ok(0xF565));
+    assert_eq!(assemble_line("RND VF, 0b1111"), Ok(0xCF0F));
+}
+
+#[test]
+pub fn test_parse_register() {
+    assert_eq!(parse_register("V0"), Ok(0));
+    assert_eq!(parse_register("VA"), Ok(10));
+    assert_eq!(parse_register("vf"), Ok(15));
+
+    for name in ["V10", "VG", "V", "A", "V-1", ""] {
+        assert_eq!(
+            parse_register(name),
+            Err(AsmError::InvalidRegister {
+                name: name.to_string()
+            })
+        );
+    }
+}
+
+#[test]
+pub fn test_assemble_line_errors() {
+    assert_eq!(
+        assemble_line("MOV V0, V1"),
+        Err(AsmError::UnknownMnemonic {
+            mnemonic: "MOV".to_string()
+        })
+    );
+    assert_eq!(
+        assemble_line("JP V1, 0x200"),
+        Err(AsmError::BadOperands {
+            line: "JP V1, 0x200".to_string()
+        })
+    );
+    assert_eq!(
+        assemble_line("LD V0, 0x100"),
+        Err(AsmError::OutOfRange {
+            value: 0x100,
+            max: 0xFF
+        })
+    );
+    assert_eq!(
+        assemble_line("LD VG, 1"),
+        Err(AsmError::InvalidRegister {
+            name: "VG".to_string()
+        })
+    );
+    assert_eq!(
+        assemble_line("LD 0x, 1"),
+        Err(AsmError::InvalidOperand {
+            operand: "0x".to_string()
+        })
+    );
+}
+
+#[test]
+pub fn test_assemble_line_inverts_disassemble() {
+    use crate::disasm::disassemble;
+
+    // every opcode that disassembles to an instruction assembles back to itself
+    for opcode in 0..=u16::MAX {
+        let text = disassemble(opcode);
+        if !text.starts_with("DW") {
+            assert_eq!(assemble_line(&text), Ok(opcode), "{}", text);
+        }
+    }
+}
+
+#[test]
+pub fn test_assemble_org() {
+    // test_call_and_return's program, with the function in its own region
+    let source = "
+        ORG 0x000
+        CALL 0x100      ; call the function twice
+        CALL 0x100
+        HALT
+
+        ORG 0x100
+        add_twice:
+            ADD V0, V1
+            ADD V0, V1
+            RET
+
+        ORG 0x120
+        CALL add_twice  ; labels resolve relative to the origin they're defined under
+    ";
+    let assembly = assemble(source).unwrap();
+    let bytes = assembly.bytes();
+
+    assert_eq!(assembly.origin(), 0x000);
+    assert_eq!(bytes[..6], [0x21, 0x00, 0x21, 0x00, 0x00, 0x00]);
+    assert!(bytes[6..0x100].iter().all(|&b| b == 0));
+    assert_eq!(bytes[0x100..0x106], [0x80, 0x14, 0x80, 0x14, 0x00, 0xEE]);
+    assert_eq!(bytes[0x120..], [0x21, 0x00]);
+
+    // the image runs as-is
+    let mut cpu = crate::cpu::CPU::new();
+    cpu.write_at(assembly.origin() as usize, bytes).unwrap();
+    cpu.reg[0] = 5;
+    cpu.reg[1] = 10;
+    cpu.run().unwrap();
+    assert_eq!(cpu.reg[0], 45);
+}
+
+#[test]
+pub fn test_assemble_errors() {
+    assert_eq!(assemble("CLS").unwrap().origin(), DEFAULT_ORIGIN);
+    assert_eq!(
+        assemble("ORG 0x200\nCLS\nORG 0x100").unwrap_err().kind(),
+        &AsmError::OriginBehind {
+            origin: 0x100,
+            addr: 0x202
+        }
+    );
+    assert_eq!(
+        assemble("JP nowhere").unwrap_err().kind(),
+        &AsmError::UndefinedLabel {
+            label: "nowhere".to_string()
+        }
+    );
+    assert_eq!(
+        assemble("here: CLS\nhere: RET").unwrap_err().kind(),
+        &AsmError::DuplicateLabel {
+            label: "here".to_string()
+        }
+    );
+    assert_eq!(
+        assemble("2nd: CLS").unwrap_err().kind(),
+        &AsmError::InvalidLabel {
+            label: "2nd".to_string()
+        }
+    );
+}
+
+#[test]
+pub fn test_assemble_data() {
+    let source = "
+        ORG 0x000
+        JP 0x010
+        arrow: DB 0x20, 0b01110000, 0xF8
+        DW 0x1234, arrow
+
+        ORG 0x010
+        LD I, arrow
+        DRW V0, V1, 3
+        HALT
+    ";
+    let assembly = assemble(source).unwrap();
+    let bytes = assembly.bytes();
+    assert_eq!(
+        bytes[..9],
+        [0x10, 0x10, 0x20, 0x70, 0xF8, 0x12, 0x34, 0x00, 0x02]
+    );
+    assert_eq!(bytes[0x10..], [0xA0, 0x02, 0xD0, 0x13, 0x00, 0x00]);
+
+    let mut cpu = crate::cpu::CPU::new();
+    cpu.write_at(0, bytes).unwrap();
+    cpu.run().unwrap();
+    assert_eq!(
+        cpu.render_ascii().lines().take(3).collect::<Vec<_>>(),
+        ["  #", " ###", "#####"].map(|row| format!("{:<64}", row))
+    );
+
+    for source in ["DB", "DB 0x100", "DB V0", "DW 1, , 2"] {
+        assert!(assemble(source).is_err(), "{}", source);
+    }
+}
+
+#[test]
+pub fn test_assemble_sprite() {
+    let source = "
+        arrow:
+            SPRITE ..#.....
+            SPRITE .###....
+            SPRITE #####...
+        bar: SPRITE .####..., ########
+    ";
+    assert_eq!(
+        assemble(source).unwrap().bytes(),
+        [0b00100000, 0b01110000, 0b11111000, 0b01111000, 0b11111111]
+    );
+
+    for row in ["", ".######", ".########", "..##..x."] {
+        assert_eq!(
+            assemble(&format!("SPRITE {}", row)).unwrap_err().kind(),
+            &AsmError::InvalidSprite {
+                row: row.to_string()
+            }
+        );
+    }
+}
+
+#[test]
+pub fn test_assemble_symbols() {
+    let source = "
+        start: LD V0, 3
+        loop:
+            ADD V1, 2
+            ADD V0, 0xFF
+            SE V0, 0
+            JP loop
+        end: HALT
+        digit: SPRITE ########
+    ";
+    let assembly = assemble(source).unwrap();
+    let symbols = assembly.symbols();
+
+    assert_eq!(symbols.len(), 4);
+    assert_eq!(symbols["start"], 0x100);
+    assert_eq!(symbols["loop"], 0x102);
+    assert_eq!(symbols["end"], 0x10A);
+    assert_eq!(symbols["digit"], 0x10C);
+    assert_eq!(
+        assembly.to_sym(),
+        "0x100 start\n0x102 loop\n0x10a end\n0x10c digit\n"
+    );
+
+    let path = std::env::temp_dir().join("sink_test_symbols.sym");
+    assembly.write_sym(&path).unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), assembly.to_sym());
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+pub fn test_assemble_forward_references() {
+    let source = "
+        JP main
+        sprite: SPRITE ########
+
+        ORG 0x110
+        main:
+            LD I, sprite
+            CALL draw
+            JP end
+        draw:
+            DRW V0, V1, 1
+            RET
+        end: HALT
+    ";
+    let assembly = assemble(source).unwrap();
+    assert_eq!(assembly.bytes()[..3], [0x11, 0x10, 0xFF]);
+    assert_eq!(
+        assembly.bytes()[0x10..],
+        [
+            0xA1, 0x02, 0x21, 0x16, 0x11, 0x1A, 0xD0, 0x11, 0x00, 0xEE, 0x00, 0x00
+        ]
+    );
+
+    // undefined labels only show up once every label is known
+    assert_eq!(
+        assemble("JP nowhere\nnowhere_else: HALT")
+            .unwrap_err()
+            .kind(),
+        &AsmError::UndefinedLabel {
+            label: "nowhere".to_string()
+        }
+    );
+}
+
+#[test]
+pub fn test_assemble_listing() {
+    let source = "ORG 0x200\nloop: ADD V0, V1 ; add the registers\n    JP loop";
+    assert_eq!(
+        assemble_listing(source).unwrap(),
+        "0x200         ORG 0x200\n\
+         0x200  80 14  loop: ADD V0, V1 ; add the registers\n\
+         0x202  12 00  JP loop\n"
+    );
+
+    let listing = assemble_listing("ORG 0x300\nCLS\n\nORG 0x310\nDB 1, 2, 3\n").unwrap();
+    let lines: Vec<&str> = listing.lines().collect();
+    assert_eq!(
+        lines,
+        [
+            "0x300         ORG 0x300",
+            "0x300  00 e0  CLS",
+            "0x302",
+            "0x310         ORG 0x310",
+            "0x310  01 02 03  DB 1, 2, 3",
+        ]
+    );
+    assert!(assemble_listing("JP nowhere").is_err());
+}
+
+#[test]
+pub fn test_assemble_error_lines() {
+    let source = "start: CLS\n    LD V0, 1\n    {}\n    JP start";
+    let errors = [
+        (
+            "FOO V0",
+            AsmError::UnknownMnemonic {
+                mnemonic: "FOO".to_string(),
+            },
+        ),
+        (
+            "ADD V0",
+            AsmError::BadOperands {
+                line: "ADD V0".to_string(),
+            },
+        ),
+        (
+            "start: RET",
+            AsmError::DuplicateLabel {
+                label: "start".to_string(),
+            },
+        ),
+        (
+            "LD V0, 0x100",
+            AsmError::OutOfRange {
+                value: 0x100,
+                max: 0xFF,
+            },
+        ),
+    ];
+    for (line, error) in errors {
+        let err = assemble(&source.replace("{}", line)).unwrap_err();
+        assert_eq!(err.line(), Some(3), "{}", line);
+        assert_eq!(err.kind(), &error, "{}", line);
+    }
+
+    let err = assemble("CLS\n\n  ld v0, vg ; oops").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "error: 'vg' is not a register: must be V0 to VF\n \
+         --> line 3, column 10\n  \
+         |\n\
+         3 |   ld v0, vg ; oops\n  \
+         |          ^^"
+    );
+    assert_eq!(assemble_line("FOO").unwrap_err().line(), None);
+}
@@ -0,0 +1,8 @@
+/// byte order of a raw buffer holding multi-byte values (e.g. an encoded float, or opcodes)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Endianness {
+    /// most significant byte first
+    Big,
+    /// least significant byte first
+    Little,
+}
@@ -1,10 +1,274 @@
+use std::{collections::HashMap, error::Error, fmt, ops::Range, time::Duration};
+
+use crate::{
+    bytes::Endianness,
+    disasm::{disassemble, find_code},
+    opcode::Opcode,
+};
+
 /// A virtual CPU that implements a subset of CHIP-8 ops.
 pub struct CPU {
-    pub reg: [u8; 16], // 16 registers can be addressed by a single hex val (0-F)
-    mem: [u8; 4096],   // 4K of RAM (0x1000): opcode written here drive the CPU FSM
-    pc: usize,         // program counter: points to the current position in memory
-    stack: [u16; 16],  // support 16 nested function-calls before "stack overflow"
-    sp: usize,         // stack pointer: points to the current position in the stack
+    pub reg: [u8; 16],       // 16 registers can be addressed by a single hex val (0-F)
+    mem: Vec<u8>,            // 4K of RAM (0x1000) by default: opcode written here drive the CPU FSM
+    program_start: usize,    // where program memory starts, after the reserved system memory
+    pc: usize,               // program counter: points to the current position in memory
+    program_len: usize,      // number of bytes last written to program memory
+    stack: Vec<u16>,         // support 16 (by default) nested calls before "stack overflow"
+    sp: usize,               // stack pointer: points to the current position in the stack
+    i: u16,                  // index register: holds memory addresses for use by opcodes
+    halted: bool,            // set once the CPU executes the halt opcode (0x0000)
+    paused: bool,            // set while the user has suspended execution
+    tick_while_paused: bool, // whether the timers keep counting down while paused
+    display: [bool; CPU::DISPLAY_WIDTH * CPU::DISPLAY_HEIGHT], // monochrome pixels, row-major
+    display_dirty: bool,     // set whenever the display is drawn to, until taken by a frontend
+    front_buffer: Option<[bool; CPU::DISPLAY_WIDTH * CPU::DISPLAY_HEIGHT]>, // presented frame (if double-buffered)
+    delay_timer: u8,                    // counts down to zero at 60Hz
+    sound_timer: u8,                    // counts down to zero at 60Hz, beeping while nonzero
+    timer_elapsed: Duration,            // real time accumulated towards the next timer tick
+    quirks: Quirks,                     // behaviours that differ between implementations
+    breakpoints: Vec<Breakpoint>,       // addresses where runs stop ahead of executing
+    opcode_counts: HashMap<u16, usize>, // how many times each opcode has been executed
+    rng: Rng,                           // source of the random numbers drawn by CXKK
+    beep_hz: f32,                       // pitch of the tone played while the sound timer is nonzero
+    trace: Option<TraceHook>,           // observes each opcode before it executes
+    audio: Option<AudioHook>,           // plays the tone for each tick of the timers
+    on_draw: Option<DrawHook>,          // repaints a frontend whenever the display changes
+}
+
+/// Errors raised while executing a CHIP-8 program.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Chip8Error {
+    /// a call was made with every stack frame already in use
+    StackOverflow { depth: usize },
+    /// a return was made without a call to return from
+    StackUnderflow,
+    /// the opcode at the given address isn't supported
+    UnknownOpcode { addr: usize, opcode: u16 },
+    /// there's no register with the given index (they're numbered 0x0 to 0xF)
+    InvalidRegister { idx: u8 },
+    /// a write of `len` bytes at `addr` would run past the end of memory
+    MemoryOverflow {
+        addr: usize,
+        len: usize,
+        size: usize,
+    },
+    /// a program of little-endian opcodes has a byte left over, so can't be made of whole opcodes
+    OddProgramLength { len: usize },
+    /// a write of `len` bytes to system memory would spill over into program memory
+    SystemMemoryOverflow { len: usize, size: usize },
+    /// the beep frequency is outside the range of human hearing
+    InaudibleFrequency { hz: f32 },
+    /// the program counter has run past the end of memory, so there's no opcode to read
+    PcOutOfBounds { pc: usize },
+}
+
+impl fmt::Display for Chip8Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Chip8Error::StackOverflow { depth } => {
+                write!(f, "Stack Overflow: exceeded {} nested calls", depth)
+            }
+            Chip8Error::StackUnderflow => write!(f, "Stack Underflow: nothing to return from"),
+            Chip8Error::UnknownOpcode { addr, opcode } => {
+                write!(f, "Unknown opcode {:04x} at {:#05x}", opcode, addr)
+            }
+            Chip8Error::InvalidRegister { idx } => {
+                write!(f, "There is no register {}: only 0 to 15", idx)
+            }
+            Chip8Error::MemoryOverflow { addr, len, size } => write!(
+                f,
+                "Cannot write {} bytes at {:#05x}: exceeds {} bytes of memory",
+                len, addr, size
+            ),
+            Chip8Error::OddProgramLength { len } => write!(
+                f,
+                "Cannot load a program of {} bytes: opcodes are two bytes each",
+                len
+            ),
+            Chip8Error::SystemMemoryOverflow { len, size } => write!(
+                f,
+                "Cannot write {} bytes of system opcodes: exceeds the {} bytes reserved for them",
+                len, size
+            ),
+            Chip8Error::InaudibleFrequency { hz } => write!(
+                f,
+                "Cannot beep at {}Hz: must be within {}Hz to {}Hz",
+                hz,
+                CPU::BEEP_RANGE.start(),
+                CPU::BEEP_RANGE.end()
+            ),
+            Chip8Error::PcOutOfBounds { pc } => write!(
+                f,
+                "Program counter {:#05x} has run past the end of memory",
+                pc
+            ),
+        }
+    }
+}
+
+impl Error for Chip8Error {}
+
+/// The result of executing a single step.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StepOutcome {
+    /// an opcode was executed, and the CPU is ready for the next
+    Executed,
+    /// the CPU has halted, so no more opcodes will be executed
+    Halted,
+    /// the CPU is paused, so no opcode was executed
+    Paused,
+    /// the program counter reached the address that was being run to
+    TargetReached,
+    /// the cycle budget was used up before anything else stopped execution
+    CycleLimit,
+    /// a breakpoint was hit, ahead of executing the opcode at the program counter
+    Breakpoint,
+}
+
+/// A location at which runs stop, optionally only when a register holds a given value.
+#[derive(Debug, Clone, PartialEq)]
+struct Breakpoint {
+    addr: usize,
+    condition: Option<(u8, u8)>, // (register, value)
+}
+
+/// Behaviours that differ between CHIP-8 implementations.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Quirks {
+    /// wrap sprite pixels that run off the edge of the display around to the opposite edge,
+    /// rather than clipping them (the starting coordinate of a sprite always wraps).
+    pub wrap_sprites: bool,
+}
+
+/// the opcode's group of instructions (0xF000)
+pub const OPCODE_GROUP_MASK: u16 = 0xF000;
+/// the first register (0x0F00)
+pub const X_MASK: u16 = 0x0F00;
+/// the second register (0x00F0)
+pub const Y_MASK: u16 = 0x00F0;
+/// a nibble, or the opcode's subgroup (0x000F)
+pub const N_MASK: u16 = 0x000F;
+/// a byte (0x00FF)
+pub const KK_MASK: u16 = 0x00FF;
+/// a memory address (0x0FFF)
+pub const NNN_MASK: u16 = 0x0FFF;
+
+/// The sprites for the hex digits 0 to F (each 4 pixels wide by 5 tall),
+/// loaded into system memory at `FONT_START`.
+pub const FONT_SET: [u8; 80] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+    0x20, 0x60, 0x20, 0x20, 0x70, // 1
+    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+    0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
+    0x90, 0x90, 0xF0, 0x10, 0x10, // 4
+    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+    0xF0, 0x10, 0x20, 0x40, 0x40, // 7
+    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+    0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
+    0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+    0xE0, 0x90, 0x90, 0x90, 0xE0, // D
+    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+    0xF0, 0x80, 0xF0, 0x80, 0x80, // F
+];
+
+/// where the font's sprites start in memory
+pub const FONT_START: usize = 0x050;
+
+/// the address of the sprite for a hex digit (only its low nibble is used, as FX29 does)
+pub fn font_address(digit: u8) -> usize {
+    FONT_START + (digit & 0xF) as usize * 5
+}
+
+/// The operand fields of an opcode (not all of which are meaningful for every opcode).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Operands {
+    /// 0x0F00: the first register
+    pub x: u8,
+    /// 0x00F0: the second register
+    pub y: u8,
+    /// 0x000F: a nibble (e.g. the height of a sprite)
+    pub n: u8,
+    /// 0x00FF: a byte
+    pub kk: u8,
+    /// 0x0FFF: a memory address
+    pub nnn: u16,
+}
+
+/// split an opcode into its operand fields (e.g. 0x73EE -> x: 0x3, kk: 0xEE, nnn: 0x3EE)
+pub fn operands(opcode: u16) -> Operands {
+    Operands {
+        x: ((opcode & X_MASK) >> 8) as u8,
+        y: ((opcode & Y_MASK) >> 4) as u8,
+        n: (opcode & N_MASK) as u8,
+        kk: (opcode & KK_MASK) as u8,
+        nnn: opcode & NNN_MASK,
+    }
+}
+
+/// 64-bit FNV-1a hash of a sequence of bytes.
+fn fnv1a(bytes: impl Iterator<Item = u8>) -> u64 {
+    const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const FNV_PRIME: u64 = 0x100000001b3;
+
+    bytes.fold(FNV_OFFSET_BASIS, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(FNV_PRIME)
+    })
+}
+
+/// A small, seedable pseudo-random number generator (SplitMix64), so runs are reproducible.
+#[derive(Debug, Clone, PartialEq)]
+struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    fn new(seed: u64) -> Rng {
+        Rng { state: seed }
+    }
+
+    fn next_u8(&mut self) -> u8 {
+        self.state = self.state.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        (z ^ (z >> 31)) as u8
+    }
+}
+
+/// Callback invoked with the address and value of each opcode, just before it executes.
+pub type TraceHook = Box<dyn FnMut(usize, u16)>;
+
+/// Callback invoked on each tick of the timers with the tone to play until the next tick:
+/// the beep frequency (in Hz) while the sound timer is nonzero, or `None` for silence.
+pub type AudioHook = Box<dyn FnMut(Option<f32>)>;
+
+/// Callback invoked with the display's pixels (see `framebuffer`) each time an opcode draws.
+pub type DrawHook = Box<dyn FnMut(&[bool])>;
+
+/// A snapshot of the CPU's registers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegisterDump {
+    pub reg: [u8; 16],
+    pub pc: usize,
+    pub i: u16,
+    pub halted: bool,
+}
+
+impl RegisterDump {
+    /// render the snapshot as a JSON object
+    pub fn to_json(&self) -> String {
+        let reg: Vec<String> = self.reg.iter().map(|r| r.to_string()).collect();
+        format!(
+            "{{ \"registers\": [{}], \"pc\": {}, \"i\": {}, \"halted\": {} }}",
+            reg.join(", "),
+            self.pc,
+            self.i,
+            self.halted,
+        )
+    }
 }
 
 impl Default for CPU {
@@ -13,79 +277,487 @@ impl Default for CPU {
     }
 }
 
-impl CPU {
-    /// indicates address space reserved for system memory
-    const RES_SYS_MEM: usize = 0x100; // 512 bytes
+/// Clones the complete machine state; hooks can't be cloned, so the clone has none installed.
+impl Clone for CPU {
+    fn clone(&self) -> Self {
+        CPU {
+            reg: self.reg,
+            pc: self.pc,
+            program_len: self.program_len,
+            mem: self.mem.clone(),
+            program_start: self.program_start,
+            stack: self.stack.clone(),
+            sp: self.sp,
+            i: self.i,
+            halted: self.halted,
+            paused: self.paused,
+            tick_while_paused: self.tick_while_paused,
+            display: self.display,
+            display_dirty: self.display_dirty,
+            front_buffer: self.front_buffer,
+            delay_timer: self.delay_timer,
+            sound_timer: self.sound_timer,
+            timer_elapsed: self.timer_elapsed,
+            quirks: self.quirks,
+            breakpoints: self.breakpoints.clone(),
+            opcode_counts: self.opcode_counts.clone(),
+            rng: self.rng.clone(),
+            beep_hz: self.beep_hz,
+            trace: None,
+            audio: None,
+            on_draw: None,
+        }
+    }
+}
+
+/// Configures and instantiates a CPU, e.g.
+/// `CPU::builder().with_quirks(quirks).with_rng_seed(42).build()`.
+///
+/// Anything left unconfigured takes the default a `CPU::new()` has.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CpuBuilder {
+    quirks: Quirks,
+    rng_seed: u64,
+    memory_size: usize,
+    program_start: usize,
+    stack_depth: usize,
+}
+
+impl Default for CpuBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CpuBuilder {
+    /// start from the default configuration
+    pub fn new() -> CpuBuilder {
+        CpuBuilder {
+            quirks: Quirks::default(),
+            rng_seed: CPU::DEFAULT_RNG_SEED,
+            memory_size: CPU::DEFAULT_MEMORY_SIZE,
+            program_start: CPU::RES_SYS_MEM,
+            stack_depth: CPU::DEFAULT_STACK_DEPTH,
+        }
+    }
+
+    /// exhibit the given quirks
+    pub fn with_quirks(self, quirks: Quirks) -> Self {
+        CpuBuilder { quirks, ..self }
+    }
+
+    /// generate the random numbers drawn by CXKK from `seed`
+    pub fn with_rng_seed(self, seed: u64) -> Self {
+        CpuBuilder {
+            rng_seed: seed,
+            ..self
+        }
+    }
+
+    /// have `size` bytes of RAM
+    pub fn with_memory_size(self, size: usize) -> Self {
+        CpuBuilder {
+            memory_size: size,
+            ..self
+        }
+    }
+
+    /// start program memory at `addr`, reserving everything below it for system memory
+    pub fn with_program_start(self, addr: usize) -> Self {
+        CpuBuilder {
+            program_start: addr,
+            ..self
+        }
+    }
+
+    /// support `depth` nested calls before overflowing the stack
+    pub fn with_stack_depth(self, depth: usize) -> Self {
+        CpuBuilder {
+            stack_depth: depth,
+            ..self
+        }
+    }
+
+    /// instantiate the configured CPU
+    ///
+    /// # Panics
+    ///
+    /// If the memory is too small to hold the font, or to start program memory within.
+    pub fn build(self) -> CPU {
+        assert!(
+            self.memory_size >= FONT_START + FONT_SET.len(),
+            "{} bytes of memory can't hold the font",
+            self.memory_size
+        );
+        assert!(
+            self.program_start < self.memory_size,
+            "program memory can't start at {:#05x} in {} bytes of memory",
+            self.program_start,
+            self.memory_size
+        );
+
+        let mut mem = vec![0; self.memory_size];
+        mem[FONT_START..FONT_START + FONT_SET.len()].copy_from_slice(&FONT_SET);
 
-    /// instantiates a default CPU
-    pub fn new() -> CPU {
         CPU {
             reg: [0; 16],
             pc: 0,
-            mem: [0; 4096],
-            stack: [0; 16],
+            program_len: 0,
+            mem,
+            program_start: self.program_start,
+            stack: vec![0; self.stack_depth],
             sp: 0,
+            i: 0,
+            halted: false,
+            paused: false,
+            tick_while_paused: false,
+            display: [false; CPU::DISPLAY_WIDTH * CPU::DISPLAY_HEIGHT],
+            display_dirty: false,
+            front_buffer: None,
+            delay_timer: 0,
+            sound_timer: 0,
+            timer_elapsed: Duration::ZERO,
+            quirks: self.quirks,
+            breakpoints: vec![],
+            opcode_counts: HashMap::new(),
+            rng: Rng::new(self.rng_seed),
+            beep_hz: CPU::DEFAULT_BEEP_HZ,
+            trace: None,
+            audio: None,
+            on_draw: None,
         }
     }
+}
+
+impl CPU {
+    /// indicates address space reserved for system memory (unless configured otherwise)
+    const RES_SYS_MEM: usize = 0x100; // 512 bytes
+
+    /// bytes of RAM, unless configured otherwise
+    pub const DEFAULT_MEMORY_SIZE: usize = 4096;
+
+    /// nested calls supported before the stack overflows, unless configured otherwise
+    pub const DEFAULT_STACK_DEPTH: usize = 16;
+
+    /// display dimensions, in pixels
+    pub const DISPLAY_WIDTH: usize = 64;
+    pub const DISPLAY_HEIGHT: usize = 32;
+
+    /// seeds the random numbers drawn by CXKK, unless another seed is given
+    pub const DEFAULT_RNG_SEED: u64 = 0x5EED;
+
+    /// the traditional pitch of the beep, and the range it can be changed within
+    pub const DEFAULT_BEEP_HZ: f32 = 440.0;
+    pub const BEEP_RANGE: std::ops::RangeInclusive<f32> = 20.0..=20_000.0;
+
+    /// the timers count down at 60Hz, independently of the CPU's clock
+    const TIMER_PERIOD: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+    /// instantiates a default CPU (see `CpuBuilder` for other configurations)
+    pub fn new() -> CPU {
+        CpuBuilder::new().build()
+    }
+
+    /// start configuring a CPU, e.g. `CPU::builder().with_rng_seed(42).build()`
+    pub fn builder() -> CpuBuilder {
+        CpuBuilder::new()
+    }
 
     /// write to the address space reserved for system opcodes
-    pub fn write_system_mem(&mut self, ops: &[u8]) {
-        if ops.len() as usize > CPU::RES_SYS_MEM {
-            panic!("Cannot exceed system memory allocation!");
+    pub fn write_system_mem(&mut self, ops: &[u8]) -> Result<(), Chip8Error> {
+        if ops.len() > self.program_start {
+            return Err(Chip8Error::SystemMemoryOverflow {
+                len: ops.len(),
+                size: self.program_start,
+            });
         }
-        let start: usize = 0x000;
-        let stop: usize = start + ops.len() as usize;
-        self.mem[start..stop].copy_from_slice(&ops);
+        self.write_at(0x000, ops)
     }
 
     /// write to the address space reserved for program opcodes
-    pub fn write_prog_mem(&mut self, ops: &[u8]) {
-        let start: usize = CPU::RES_SYS_MEM;
-        let stop: usize = start + ops.len() as usize;
-        self.mem[start..stop].copy_from_slice(&ops);
+    pub fn write_prog_mem(&mut self, ops: &[u8]) -> Result<(), Chip8Error> {
+        self.write_at(self.program_start, ops)?;
+        self.program_len = ops.len();
+        Ok(())
+    }
+
+    /// write a program to program memory, converting its opcodes from the given byte order
+    /// (CHIP-8 stores opcodes big-endian, but dumps from other tools may not)
+    pub fn load_program(&mut self, bytes: &[u8], byte_order: Endianness) -> Result<(), Chip8Error> {
+        match byte_order {
+            Endianness::Big => self.write_prog_mem(bytes),
+            Endianness::Little => {
+                if !bytes.len().is_multiple_of(2) {
+                    return Err(Chip8Error::OddProgramLength { len: bytes.len() });
+                }
+                let swapped: Vec<u8> = bytes
+                    .chunks(2)
+                    .flat_map(|pair| [pair[1], pair[0]])
+                    .collect();
+                self.write_prog_mem(&swapped)
+            }
+        }
+    }
+
+    /// overwrite the opcode at `addr` (big-endian, as read by the CPU), e.g. mid-run from a debugger.
+    /// opcodes are decoded afresh on every step, so the patch takes effect immediately.
+    pub fn patch(&mut self, addr: usize, opcode: u16) -> Result<(), Chip8Error> {
+        self.write_at(addr, &opcode.to_be_bytes())
+    }
+
+    /// list each memory cell that differs from another CPU's, as `(addr, self_byte, other_byte)`
+    pub fn mem_diff(&self, other: &CPU) -> Vec<(usize, u8, u8)> {
+        self.mem
+            .iter()
+            .zip(other.mem.iter())
+            .enumerate()
+            .filter(|(_, (a, b))| a != b)
+            .map(|(addr, (a, b))| (addr, *a, *b))
+            .collect()
+    }
+
+    /// a stable (64-bit FNV-1a) hash of the loaded program, for identifying exactly which ROM is loaded
+    pub fn rom_hash(&self) -> u64 {
+        let start = self.program_start;
+        fnv1a(self.mem[start..start + self.program_len].iter().copied())
+    }
+
+    /// a stable (64-bit FNV-1a) hash of the display's pixels (one byte per pixel, 1 if lit),
+    /// for checking a program drew exactly what was expected
+    pub fn framebuffer_hash(&self) -> u64 {
+        fnv1a(self.framebuffer().iter().map(|&lit| lit as u8))
+    }
+
+    /// write bytes to memory starting at an arbitrary address
+    pub fn write_at(&mut self, addr: usize, bytes: &[u8]) -> Result<(), Chip8Error> {
+        let overflow = || Chip8Error::MemoryOverflow {
+            addr,
+            len: bytes.len(),
+            size: self.mem.len(),
+        };
+        let stop = addr.checked_add(bytes.len()).ok_or_else(overflow)?;
+        if stop > self.mem.len() {
+            return Err(overflow());
+        }
+        self.mem[addr..stop].copy_from_slice(bytes);
+        Ok(())
+    }
+
+    /// the address program memory starts at
+    pub fn program_start(&self) -> usize {
+        self.program_start
+    }
+
+    /// disassemble the loaded program into (address, opcode, mnemonic) rows.
+    /// only instructions reachable from the start of the program are decoded (see `find_code`);
+    /// every other byte, such as a sprite, is shown as a row of data (e.g. "DB 0x12").
+    pub fn disassemble_program(&self) -> Vec<(usize, u16, String)> {
+        let start = self.program_start();
+        let program = &self.mem[start..start + self.program_len];
+        let code = find_code(program, start);
+
+        let mut rows = Vec::new();
+        let mut idx = 0;
+        while idx < program.len() {
+            if code[idx] {
+                let opcode = u16::from_be_bytes([program[idx], program[idx + 1]]);
+                rows.push((start + idx, opcode, disassemble(opcode)));
+                idx += 2;
+            } else {
+                let byte = program[idx];
+                rows.push((start + idx, byte as u16, format!("DB 0x{:02X}", byte)));
+                idx += 1;
+            }
+        }
+        rows
+    }
+
+    /// number of bytes loaded into program memory by the last `write_prog_mem`
+    pub fn program_len(&self) -> usize {
+        self.program_len
+    }
+
+    /// install a hook that observes each opcode as it is executed
+    pub fn set_trace_hook(&mut self, hook: TraceHook) {
+        self.trace = Some(hook);
+    }
+
+    /// the display's pixels, row-major (index with `y * DISPLAY_WIDTH + x`).
+    /// when double-buffered, this is the frame that was last presented.
+    pub fn framebuffer(&self) -> &[bool] {
+        match &self.front_buffer {
+            Some(front) => front,
+            None => &self.display,
+        }
+    }
+
+    /// draw into a back buffer, which is only shown (by `framebuffer`, and to the draw
+    /// callback) once `present` is called, so frontends never see a half-drawn frame.
+    /// the display is single-buffered by default.
+    pub fn set_double_buffered(&mut self, enabled: bool) {
+        self.front_buffer = enabled.then_some(self.display);
+    }
+
+    /// show everything drawn into the back buffer so far (does nothing if single-buffered)
+    pub fn present(&mut self) {
+        if self.front_buffer.is_some() {
+            self.front_buffer = Some(self.display);
+            self.display_changed();
+        }
+    }
+
+    /// render the display as text, one line per row ('#' for lit pixels, ' ' for unlit)
+    pub fn render_ascii(&self) -> String {
+        self.render_ascii_with('#', ' ')
+    }
+
+    /// render the display as text, one line per row, using the given characters for
+    /// lit and unlit pixels (e.g. '█' and ' ', or braille)
+    pub fn render_ascii_with(&self, on: char, off: char) -> String {
+        let mut text = String::with_capacity((CPU::DISPLAY_WIDTH + 1) * CPU::DISPLAY_HEIGHT);
+        for row in self.framebuffer().chunks(CPU::DISPLAY_WIDTH) {
+            text.extend(row.iter().map(|&lit| if lit { on } else { off }));
+            text.push('\n');
+        }
+        text
+    }
+
+    /// whether the display has been drawn to since this was last called (clearing the flag),
+    /// for frontends that poll once per frame rather than installing a draw callback
+    pub fn take_dirty(&mut self) -> bool {
+        std::mem::take(&mut self.display_dirty)
+    }
+
+    /// install a callback that repaints the display whenever it changes (see `DrawHook`)
+    pub fn set_draw_callback(&mut self, hook: DrawHook) {
+        self.on_draw = Some(hook);
+    }
+
+    /// install a callback that plays the beep (see `AudioHook`)
+    pub fn set_audio_hook(&mut self, hook: AudioHook) {
+        self.audio = Some(hook);
+    }
+
+    /// change the pitch of the beep played while the sound timer is nonzero
+    pub fn set_beep_frequency(&mut self, hz: f32) -> Result<(), Chip8Error> {
+        if !CPU::BEEP_RANGE.contains(&hz) {
+            return Err(Chip8Error::InaudibleFrequency { hz });
+        }
+        self.beep_hz = hz;
+        Ok(())
+    }
+
+    /// the pitch of the beep, in Hz
+    pub fn beep_frequency(&self) -> f32 {
+        self.beep_hz
+    }
+
+    /// current value of the delay timer
+    pub fn delay_timer(&self) -> u8 {
+        self.delay_timer
+    }
+
+    /// current value of the sound timer
+    pub fn sound_timer(&self) -> u8 {
+        self.sound_timer
+    }
+
+    /// count both timers down by a single 60Hz tick
+    pub fn tick_timers(&mut self) {
+        if let Some(audio) = self.audio.as_mut() {
+            audio((self.sound_timer > 0).then_some(self.beep_hz));
+        }
+        self.delay_timer = self.delay_timer.saturating_sub(1);
+        self.sound_timer = self.sound_timer.saturating_sub(1);
+    }
+
+    /// advance the timers by the real time that has elapsed,
+    /// carrying any fraction of a tick over to the next call.
+    pub fn tick(&mut self, elapsed: Duration) {
+        if self.paused && !self.tick_while_paused {
+            return;
+        }
+        self.timer_elapsed += elapsed;
+        while self.timer_elapsed >= CPU::TIMER_PERIOD {
+            self.timer_elapsed -= CPU::TIMER_PERIOD;
+            self.tick_timers();
+        }
+    }
+
+    /// set register `idx` (0x0 to 0xF)
+    pub fn set_reg(&mut self, idx: u8, val: u8) -> Result<(), Chip8Error> {
+        let reg = self
+            .reg
+            .get_mut(idx as usize)
+            .ok_or(Chip8Error::InvalidRegister { idx })?;
+        *reg = val;
+        Ok(())
+    }
+
+    /// read register `idx` (0x0 to 0xF)
+    pub fn get_reg(&self, idx: u8) -> Result<u8, Chip8Error> {
+        self.reg
+            .get(idx as usize)
+            .copied()
+            .ok_or(Chip8Error::InvalidRegister { idx })
+    }
+
+    /// take a snapshot of the registers
+    pub fn dump_registers(&self) -> RegisterDump {
+        RegisterDump {
+            reg: self.reg,
+            pc: self.pc,
+            i: self.i,
+            halted: self.halted,
+        }
+    }
+
+    /// read a region of memory (`None` if any of it lies beyond the end of memory)
+    pub fn mem_slice(&self, range: Range<usize>) -> Option<&[u8]> {
+        self.mem.get(range)
+    }
+
+    /// render a region of memory as address-labeled rows of 16 bytes
+    /// (the range is clipped to the extent of memory)
+    pub fn hex_dump(&self, range: Range<usize>) -> String {
+        let end = range.end.min(self.mem.len());
+        let start = range.start.min(end);
+
+        let mut dump = String::new();
+        for (row, bytes) in self.mem[start..end].chunks(16).enumerate() {
+            let bytes: Vec<String> = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+            dump += &format!("{:#05x}: {}\n", start + row * 16, bytes.join(" "));
+        }
+        dump
+    }
+
+    /// the opcode that the next call to `step` will execute
+    pub fn next_opcode(&self) -> Result<u16, Chip8Error> {
+        self.read_opcode()
     }
 
     /// read in the current operation referenced by the program_counter
-    fn read_opcode(&self) -> u16 {
-        let op_byte1 = self.mem[self.pc] as u16; // 0b00000000XXXXXXXX
-        let op_byte2 = self.mem[self.pc + 1] as u16; // 0b00000000YYYYYYYY
-
-        (op_byte1 << 8) | op_byte2 // 0bXXXXXXXXYYYYYYYY
-    }
-    /// decode the passed CHIP-8 opcode into its components:
-    /// n ---> 0x000F (number of bytes)
-    /// x ---> 0x0F00 (CPU register)
-    /// y ---> 0x00F0 (CPU register)
-    /// c ---> 0xF000 (Opcode group)
-    /// d ---> 0x000F (Opcode subgroup -- used in different contexts to 'n')
-    /// kk --> 0x00FF (Integer)
-    /// nnn -> 0x0FFF (Memory address)
-    ///
-    /// Three main opcode forms:
-    ///
-    /// - adding val to register (e.g. 0x73EE -> "add 238 [0xEE] to register 3")
-    ///     - let (c, x, kk) = (0x7, 0x3, 0xEE);
-    ///
-    /// - jump to memory address (e.g. 0x1200 -> "jump to mem location 0x200")
-    ///     - let (c, nnn) = (0x1, 0x200);
-    ///
-    /// - bitwise OR on two registers (e.g. 0x8231 -> "x = x | y")
-    ///     - let (c, x, y, d) = (0x8, 0x2, 0x3, 0x1);
-    ///
-    fn decode(&self, opcode: &u16) -> (u8, u8, u8, u8) {
-        (
-            ((opcode & 0xF000) >> 12) as u8,
-            ((opcode & 0x0F00) >> 8) as u8,
-            ((opcode & 0x00F0) >> 4) as u8,
-            ((opcode & 0x000F) >> 0) as u8,
-        )
+    fn read_opcode(&self) -> Result<u16, Chip8Error> {
+        let Some(&[op_byte1, op_byte2]) = self.mem.get(self.pc..self.pc.saturating_add(2)) else {
+            return Err(Chip8Error::PcOutOfBounds { pc: self.pc });
+        };
+        // 0bXXXXXXXX00000000 | 0b00000000YYYYYYYY
+        Ok((op_byte1 as u16) << 8 | op_byte2 as u16)
+    }
+
+    /// store the decimal digits of register x in memory at I, I+1 and I+2 (hundreds first)
+    fn store_bcd(&mut self, x: u8) -> Result<(), Chip8Error> {
+        let val = self.reg[x as usize];
+        self.write_at(self.i as usize, &[val / 100, val / 10 % 10, val % 10])
     }
 
     /// add a new entry to the call-stack
-    pub fn call(&mut self, addr: u16) {
+    pub fn call(&mut self, addr: u16) -> Result<(), Chip8Error> {
         // cannot reference beyond the address space allocated to the stack!
-        if self.sp > self.stack.len() {
-            panic!("Stack Overflow");
+        if self.sp >= self.stack.len() {
+            return Err(Chip8Error::StackOverflow {
+                depth: self.stack.len(),
+            });
         }
 
         // keep track of where the program counter has been pointing:
@@ -95,103 +767,1600 @@ impl CPU {
         self.stack[self.sp] = self.pc as u16;
         self.sp += 1;
         self.pc = addr as usize;
+        Ok(())
+    }
+
+    /// how many times each mnemonic has been executed, hottest first
+    pub fn profile_report(&self) -> Vec<(String, usize)> {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for (opcode, count) in &self.opcode_counts {
+            *counts.entry(disassemble(*opcode)).or_default() += count;
+        }
+
+        // (ties are broken alphabetically, so the report is stable)
+        let mut report: Vec<(String, usize)> = counts.into_iter().collect();
+        report.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        report
+    }
+
+    /// number of calls that have yet to return
+    pub fn call_depth(&self) -> usize {
+        self.sp
+    }
+
+    /// the return addresses of the live calls, outermost first
+    pub fn call_frames(&self) -> Vec<u16> {
+        self.stack[..self.sp].to_vec()
     }
 
     /// move down the call-stack
-    pub fn ret(&mut self) {
+    pub fn ret(&mut self) -> Result<(), Chip8Error> {
         if self.sp == 0 {
-            panic!("Stack Underflow!")
+            return Err(Chip8Error::StackUnderflow);
         }
         self.sp -= 1;
         self.pc = self.stack[self.sp] as usize;
+        Ok(())
+    }
+
+    /// stop runs ahead of executing the opcode at `addr`
+    pub fn add_breakpoint(&mut self, addr: usize) {
+        self.breakpoints.push(Breakpoint {
+            addr,
+            condition: None,
+        });
+    }
+
+    /// stop runs ahead of executing the opcode at `addr`, but only when `reg[reg] == value`
+    /// (`reg` must be 0x0 to 0xF)
+    pub fn add_conditional_breakpoint(
+        &mut self,
+        addr: usize,
+        reg: u8,
+        value: u8,
+    ) -> Result<(), Chip8Error> {
+        if reg as usize >= self.reg.len() {
+            return Err(Chip8Error::InvalidRegister { idx: reg });
+        }
+        self.breakpoints.push(Breakpoint {
+            addr,
+            condition: Some((reg, value)),
+        });
+        Ok(())
     }
 
-    pub fn run(&mut self) {
-        loop {
-            let opcode = self.read_opcode();
-            self.pc += 2; // each mem blk is u8 and can hold half a u16 instruction,
-            // so shift the program-counter to the next instruction that's
-            // sitting two blocks away from the current instruction
+    /// remove every breakpoint (conditional or not) at `addr`
+    pub fn remove_breakpoint(&mut self, addr: usize) {
+        self.breakpoints.retain(|bp| bp.addr != addr);
+    }
 
-            let nnn = opcode & 0x0FFF;
-            //let kk = (opcode & 0x00FF) as u8;
+    /// whether any breakpoint triggers at the current program counter
+    fn at_breakpoint(&self) -> bool {
+        self.breakpoints.iter().any(|bp| {
+            bp.addr == self.pc
+                && match bp.condition {
+                    Some((reg, value)) => self.reg[reg as usize] == value,
+                    None => true,
+                }
+        })
+    }
 
-            match self.decode(&opcode) {
-                (0, 0, 0, 0) => return,
-                (0, 0, 0xE, 0xE) => self.ret(),
-                (0x2, _, _, _) => self.call(nnn),
-                (0x8, x, y, 0x4) => self.add_xy(x, y),
-                _ => todo!("implement remaining opcodes!"),
+    /// execute opcodes until the CPU halts (or is paused, or hits a breakpoint).
+    ///
+    /// breakpoints are only checked after the first opcode, so a run can resume from one;
+    /// returns the number of opcodes executed (including the final halt opcode).
+    pub fn run(&mut self) -> Result<usize, Chip8Error> {
+        let mut cycles = 0;
+        while !(self.halted || self.paused) {
+            let outcome = self.step()?;
+            cycles += 1;
+            if outcome != StepOutcome::Executed || self.at_breakpoint() {
+                break;
             }
         }
+        Ok(cycles)
     }
 
-    fn add_xy(&mut self, x: u8, y: u8) {
-        let lhs = self.reg[x as usize];
-        let rhs = self.reg[y as usize];
+    /// execute at most `max_cycles` opcodes, stopping early (as `run` does) if the CPU halts,
+    /// is paused, errors, or hits a breakpoint.
+    pub fn run_for(&mut self, max_cycles: usize) -> Result<StepOutcome, Chip8Error> {
+        for _ in 0..max_cycles {
+            match self.step()? {
+                StepOutcome::Executed if self.at_breakpoint() => {
+                    return Ok(StepOutcome::Breakpoint);
+                }
+                StepOutcome::Executed => continue,
+                outcome => return Ok(outcome),
+            }
+        }
+        Ok(StepOutcome::CycleLimit)
+    }
 
-        let (wrapped_val, overflow) = rhs.overflowing_add(lhs);
-        self.reg[x as usize] = wrapped_val;
+    /// execute opcodes one at a time, yielding the address and value of each as it executes
+    /// (including the final halt opcode). Iteration ends when the CPU halts, is paused,
+    /// hits a breakpoint, or after yielding an error.
+    pub fn instructions(&mut self) -> impl Iterator<Item = Result<(usize, u16), Chip8Error>> {
+        let mut done = false;
+        std::iter::from_fn(move || {
+            if done || self.halted || self.paused {
+                return None;
+            }
+            let pc = self.pc;
+            let result = self
+                .read_opcode()
+                .and_then(|opcode| self.step().map(|outcome| (outcome, opcode)));
+            done = match result {
+                Ok((StepOutcome::Executed, _)) => self.at_breakpoint(),
+                _ => true,
+            };
+            Some(result.map(|(_, opcode)| (pc, opcode)))
+        })
+    }
 
-        // last register is used as a carry-flag
-        // which indicates an operation has overflowed the u8 register size
-        if overflow {
-            self.reg[0xF] = 1;
-        } else {
-            self.reg[0xF] = 0;
+    /// execute opcodes until the program counter reaches `addr` (without executing it),
+    /// stopping early if the CPU halts, is paused, errors, or uses up `max_cycles`.
+    pub fn run_until(&mut self, addr: usize, max_cycles: usize) -> Result<StepOutcome, Chip8Error> {
+        for _ in 0..max_cycles {
+            if self.pc == addr {
+                return Ok(StepOutcome::TargetReached);
+            }
+            match self.step()? {
+                StepOutcome::Executed if self.pc != addr && self.at_breakpoint() => {
+                    return Ok(StepOutcome::Breakpoint);
+                }
+                StepOutcome::Executed => continue,
+                outcome => return Ok(outcome),
+            }
+        }
+        match self.pc == addr {
+            true => Ok(StepOutcome::TargetReached),
+            false => Ok(StepOutcome::CycleLimit),
         }
     }
-}
 
-#[test]
-/// validating expected CPU behaviour using the addition opcode
-/// NOTE: test executable (generated with 'cargo test') is stored under 'target/debug/deps/'
-pub fn test_addition() {
-    let mut cpu = CPU::new();
+    /// execute the opcode referenced by the program counter
+    /// (once halted, or while paused, this does nothing)
+    pub fn step(&mut self) -> Result<StepOutcome, Chip8Error> {
+        if self.halted {
+            return Ok(StepOutcome::Halted);
+        }
+        if self.paused {
+            return Ok(StepOutcome::Paused);
+        }
 
-    // define some values for testing
-    let summands = [5, 10, 10, 10];
-    let expected_sum = summands.iter().sum(); // 5 + 10 + 10 + 10 = 35
+        let opcode = self.read_opcode()?;
+        if let Some(trace) = self.trace.as_mut() {
+            trace(self.pc, opcode);
+        }
+        *self.opcode_counts.entry(opcode).or_default() += 1;
+        self.pc += 2; // each mem blk is u8 and can hold half a u16 instruction,
+        // so shift the program-counter to the next instruction that's
+        // sitting two blocks away from the current instruction
 
-    // load registers with summands
-    for (idx, val) in summands.iter().enumerate() {
-        cpu.reg[idx] = *val;
+        let unknown = Chip8Error::UnknownOpcode {
+            addr: self.pc - 2,
+            opcode,
+        };
+        let Ok(instruction) = Opcode::try_from(opcode) else {
+            return Err(unknown);
+        };
+
+        match instruction {
+            Opcode::Halt => {
+                self.halted = true;
+                return Ok(StepOutcome::Halted);
+            }
+            Opcode::Clear => self.clear_display(),
+            Opcode::Return => self.ret()?,
+            Opcode::Jump { nnn } => self.pc = nnn as usize,
+            Opcode::Call { nnn } => self.call(nnn)?,
+            Opcode::SkipEqImmediate { x, kk } => self.skip_if(self.reg[x as usize] == kk),
+            Opcode::LoadImmediate { x, kk } => self.reg[x as usize] = kk,
+            Opcode::AddImmediate { x, kk } => {
+                self.reg[x as usize] = self.reg[x as usize].wrapping_add(kk)
+            }
+            Opcode::AluAdd { x, y } => self.add_xy(x, y),
+            Opcode::AluSub { x, y } => self.sub_xy(x, y),
+            Opcode::AluSubn { x, y } => self.subn_xy(x, y),
+            Opcode::LoadIndex { nnn } => self.i = nnn,
+            Opcode::Random { x, kk } => self.reg[x as usize] = self.rng.next_u8() & kk,
+            Opcode::Draw { x, y, n } => self.draw(x, y, n),
+            Opcode::LoadDelay { x } => self.reg[x as usize] = self.delay_timer,
+            Opcode::SetDelay { x } => self.delay_timer = self.reg[x as usize],
+            Opcode::SetSound { x } => self.sound_timer = self.reg[x as usize],
+            Opcode::LoadFont { x } => self.i = font_address(self.reg[x as usize]) as u16,
+            Opcode::StoreBcd { x } => self.store_bcd(x)?,
+            // (valid instructions this CPU doesn't implement yet)
+            _ => return Err(unknown),
+        }
+        Ok(StepOutcome::Executed)
     }
 
-    (cpu.mem[0], cpu.mem[1]) = (0x80, 0x14); // 0x8014 (8: two registers [0 & 1], 4: addition)
-    (cpu.mem[2], cpu.mem[3]) = (0x80, 0x24); // 0x8024 (8: two registers [0 & 2], 4: addition)
-    (cpu.mem[4], cpu.mem[5]) = (0x80, 0x34); // 0x8034 (8: two registers [0 & 3], 4: addition)
-    //
-    cpu.run();
-    assert_eq!(cpu.reg[0], expected_sum);
-}
+    /// whether the CPU has executed the halt opcode (0x0000)
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
 
-#[test]
-pub fn test_call_and_return() {
-    // instantiate a virtual CPU
-    let mut cpu = CPU::new();
+    /// suspend execution, without losing any state
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
 
-    // define some values for testing
-    let args = [5, 10];
-    let expected_sum = args[0] + args[1] * 2 + args[1] * 2; // 5 + (10 * 2 ) * 2
+    /// continue execution from where it was paused
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
 
-    // load the values into the registiers
-    for (idx, val) in args.iter().enumerate() {
-        cpu.reg[idx] = *val;
+    /// whether execution has been suspended by `pause()`
+    pub fn is_paused(&self) -> bool {
+        self.paused
     }
 
-    // call the function loaded at 0x100 twice
-    let call_func_twice: [u8; 6] = [0x21, 0x00, 0x21, 0x00, 0x00, 0x00];
-    cpu.write_system_mem(&call_func_twice);
+    /// choose whether the timers keep counting down while paused (they're frozen by default)
+    pub fn set_tick_while_paused(&mut self, enabled: bool) {
+        self.tick_while_paused = enabled;
+    }
 
-    // define a function composed of opcodes
-    let add_twice_func: [u8; 6] = [
-        0x80, 0x14, // ADD reg 1 to reg 0
-        0x80, 0x14, // --||--
-        0x00, 0xEE, // RETURN
+    /// skip over the next opcode when the condition holds
+    fn skip_if(&mut self, condition: bool) {
+        if condition {
+            self.pc += 2;
+        }
+    }
+
+    fn add_xy(&mut self, x: u8, y: u8) {
+        let lhs = self.reg[x as usize];
+        let rhs = self.reg[y as usize];
+
+        let (wrapped_val, overflow) = rhs.overflowing_add(lhs);
+        self.reg[x as usize] = wrapped_val;
+        self.set_carry(overflow);
+    }
+
+    /// Vx = Vx - Vy, with VF = NOT borrow
+    fn sub_xy(&mut self, x: u8, y: u8) {
+        let lhs = self.reg[x as usize];
+        let rhs = self.reg[y as usize];
+        self.sub_into(x, lhs, rhs);
+    }
+
+    /// Vx = Vy - Vx, with VF = NOT borrow
+    fn subn_xy(&mut self, x: u8, y: u8) {
+        let lhs = self.reg[y as usize];
+        let rhs = self.reg[x as usize];
+        self.sub_into(x, lhs, rhs);
+    }
+
+    /// store lhs - rhs in Vx, then set VF to 1 if no borrow occurred (i.e. lhs >= rhs).
+    ///
+    /// both operands are read before anything is written, so the flag reflects the original
+    /// operands even when Vx or Vy is VF; when Vx is VF the flag (written last) takes precedence.
+    fn sub_into(&mut self, x: u8, lhs: u8, rhs: u8) {
+        let (wrapped_val, borrow) = lhs.overflowing_sub(rhs);
+        self.reg[x as usize] = wrapped_val;
+        self.set_carry(!borrow);
+    }
+
+    /// set the flag register (VF) to 1 or 0.
+    ///
+    /// the last register is used as a flag, indicating an operation has overflowed (or not
+    /// borrowed from) the u8 register size. ops must call this after writing their result,
+    /// so that the flag takes precedence when the result register is VF itself.
+    fn set_carry(&mut self, flag: bool) {
+        self.reg[0xF] = flag as u8;
+    }
+
+    fn clear_display(&mut self) {
+        self.display.fill(false);
+        self.drawn();
+    }
+
+    /// after an opcode draws: single-buffered displays show the change straight away,
+    /// but double-buffered ones wait for it to be presented
+    fn drawn(&mut self) {
+        if self.front_buffer.is_none() {
+            self.display_changed();
+        }
+    }
+
+    /// let any frontend know the visible display has changed
+    fn display_changed(&mut self) {
+        self.display_dirty = true;
+        if let Some(on_draw) = self.on_draw.as_mut() {
+            on_draw(self.front_buffer.as_ref().unwrap_or(&self.display));
+        }
+    }
+
+    /// XOR an n-byte sprite (read from memory starting at I) onto the display at (Vx, Vy).
+    /// Each byte is one 8-pixel row of the sprite, with the MSB as the left-most pixel.
+    fn draw(&mut self, x: u8, y: u8, n: u8) {
+        let (w, h) = (CPU::DISPLAY_WIDTH, CPU::DISPLAY_HEIGHT);
+
+        // the starting coordinate always wraps onto the display
+        let x0 = self.reg[x as usize] as usize % w;
+        let y0 = self.reg[y as usize] as usize % h;
+
+        let mut collision = false;
+        for row in 0..n as usize {
+            let sprite = self.mem[(self.i as usize + row) % self.mem.len()];
+            let py = y0 + row;
+            if py >= h && !self.quirks.wrap_sprites {
+                break; // the rest of the sprite is clipped
+            }
+
+            for col in 0..8 {
+                if (sprite >> (7 - col)) & 1 == 0 {
+                    continue; // unset sprite bits leave the display as-is
+                }
+                let px = x0 + col;
+                if px >= w && !self.quirks.wrap_sprites {
+                    break;
+                }
+
+                // turning a set pixel off is a collision
+                let idx = (py % h) * w + (px % w);
+                collision |= self.display[idx];
+                self.display[idx] ^= true;
+            }
+        }
+
+        // VF is written last, so it holds the collision flag even if it was Vx or Vy
+        self.set_carry(collision);
+        self.drawn();
+    }
+}
+
+#[test]
+/// validating expected CPU behaviour using the addition opcode
+/// NOTE: test executable (generated with 'cargo test') is stored under 'target/debug/deps/'
+pub fn test_addition() {
+    let mut cpu = CPU::new();
+
+    // define some values for testing
+    let summands = [5, 10, 10, 10];
+    let expected_sum = summands.iter().sum(); // 5 + 10 + 10 + 10 = 35
+
+    // load registers with summands
+    for (idx, val) in summands.iter().enumerate() {
+        cpu.reg[idx] = *val;
+    }
+
+    (cpu.mem[0], cpu.mem[1]) = (0x80, 0x14); // 0x8014 (8: two registers [0 & 1], 4: addition)
+    (cpu.mem[2], cpu.mem[3]) = (0x80, 0x24); // 0x8024 (8: two registers [0 & 2], 4: addition)
+    (cpu.mem[4], cpu.mem[5]) = (0x80, 0x34); // 0x8034 (8: two registers [0 & 3], 4: addition)
+    //
+    cpu.run().unwrap();
+    assert_eq!(cpu.reg[0], expected_sum);
+}
+
+#[test]
+pub fn test_addition_overflow() {
+    let mut cpu = CPU::new();
+    cpu.reg[..3].copy_from_slice(&[200, 100, 10]);
+
+    // 200 + 100 wraps past 255 and sets the carry flag
+    (cpu.mem[0], cpu.mem[1]) = (0x80, 0x14); // 0x8014 (reg 0 += reg 1)
+    cpu.run().unwrap();
+    assert_eq!(cpu.reg[0], 44); // 300 - 256
+    assert_eq!(cpu.reg[0xF], 1);
+
+    // chaining a non-overflowing addition clears the flag again
+    rerun(&mut cpu, &[0x80, 0x24, 0x00, 0x00]); // 0x8024 (reg 0 += reg 2)
+    assert_eq!(cpu.reg[0], 54);
+    assert_eq!(cpu.reg[0xF], 0);
+}
+
+#[test]
+pub fn test_addition_without_overflow() {
+    let mut cpu = CPU::new();
+    cpu.reg[..2].copy_from_slice(&[155, 100]);
+    (cpu.mem[0], cpu.mem[1]) = (0x80, 0x14); // 0x8014: 155 + 100 = 255 just fits
+    cpu.run().unwrap();
+    assert_eq!(cpu.reg[0], 255);
+    assert_eq!(cpu.reg[0xF], 0);
+}
+
+#[test]
+pub fn test_addition_into_flag_register() {
+    let mut cpu = CPU::new();
+    (cpu.reg[0xF], cpu.reg[0]) = (200, 100);
+    (cpu.mem[0], cpu.mem[1]) = (0x8F, 0x04); // 0x8F04 (reg F += reg 0)
+    cpu.run().unwrap();
+    // the sum (44) is overwritten by the carry, which is written last
+    assert_eq!(cpu.reg[0xF], 1);
+
+    let mut cpu = CPU::new();
+    (cpu.reg[0xF], cpu.reg[0]) = (20, 100);
+    (cpu.mem[0], cpu.mem[1]) = (0x8F, 0x04);
+    cpu.run().unwrap();
+    assert_eq!(cpu.reg[0xF], 0);
+}
+
+#[test]
+pub fn test_call_and_return() {
+    // instantiate a virtual CPU
+    let mut cpu = CPU::new();
+
+    // define some values for testing
+    let args = [5, 10];
+    let expected_sum = args[0] + args[1] * 2 + args[1] * 2; // 5 + (10 * 2 ) * 2
+
+    // load the values into the registiers
+    for (idx, val) in args.iter().enumerate() {
+        cpu.reg[idx] = *val;
+    }
+
+    // call the function loaded at 0x100 twice
+    let call_func_twice: [u8; 6] = [0x21, 0x00, 0x21, 0x00, 0x00, 0x00];
+    cpu.write_system_mem(&call_func_twice).unwrap();
+
+    // define a function composed of opcodes
+    let add_twice_func: [u8; 6] = [
+        0x80, 0x14, // ADD reg 1 to reg 0
+        0x80, 0x14, // --||--
+        0x00, 0xEE, // RETURN
     ];
-    cpu.write_prog_mem(&add_twice_func);
+    cpu.write_prog_mem(&add_twice_func).unwrap();
 
-    cpu.run();
+    cpu.run().unwrap();
     assert_eq!(cpu.reg[0], expected_sum);
 }
+
+#[test]
+pub fn test_dump_registers() {
+    let mut cpu = CPU::new();
+    cpu.reg[0] = 5;
+    cpu.reg[1] = 10;
+    cpu.write_system_mem(&[0x80, 0x14, 0x00, 0x00]).unwrap();
+
+    assert!(!cpu.dump_registers().halted);
+    cpu.run().unwrap();
+
+    let dump = cpu.dump_registers();
+    assert_eq!(dump.reg[0], 15);
+    assert_eq!(dump.pc, 4);
+    assert!(dump.halted);
+    assert_eq!(
+        dump.to_json(),
+        "{ \"registers\": [15, 10, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0], \
+         \"pc\": 4, \"i\": 0, \"halted\": true }"
+    );
+}
+
+#[test]
+pub fn test_trace_hook() {
+    use std::{cell::RefCell, rc::Rc};
+
+    let mut cpu = CPU::new();
+    cpu.write_system_mem(&[0x80, 0x14, 0x80, 0x24, 0x00, 0x00])
+        .unwrap();
+
+    // record each traced opcode so it can be inspected after the run
+    let traced = Rc::new(RefCell::new(vec![]));
+    let sink = Rc::clone(&traced);
+    cpu.set_trace_hook(Box::new(move |pc, opcode| {
+        sink.borrow_mut().push((pc, opcode))
+    }));
+
+    cpu.run().unwrap();
+    assert_eq!(*traced.borrow(), [(0, 0x8014), (2, 0x8024), (4, 0x0000)]);
+}
+
+#[test]
+pub fn test_hex_dump() {
+    let mut cpu = CPU::new();
+    cpu.write_prog_mem(&[0x80, 0x14, 0x00, 0xEE]).unwrap();
+
+    let dump = cpu.hex_dump(0x0F8..0x118);
+    let rows: Vec<&str> = dump.lines().collect();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(
+        rows[0],
+        "0x0f8: 00 00 00 00 00 00 00 00 80 14 00 ee 00 00 00 00"
+    );
+    assert!(rows[1].starts_with("0x108: 00"));
+
+    // clipped to the end of memory
+    assert_eq!(cpu.hex_dump(0xFF8..0x2000).lines().count(), 1);
+    assert_eq!(cpu.hex_dump(0x2000..0x3000), "");
+}
+
+/// draw the sprite at 0x200 with D015, for the given x/y coordinates (held in reg 0/1)
+#[cfg(test)]
+fn draw_sprite(cpu: &mut CPU, sprite: &[u8], x: u8, y: u8) {
+    cpu.mem[0x200..0x200 + sprite.len()].copy_from_slice(sprite);
+    (cpu.reg[0], cpu.reg[1]) = (x, y);
+
+    // I = 0x200, draw a sprite of sprite.len() rows, halt
+    let ops = [0xA2, 0x00, 0xD0, 0x10 | sprite.len() as u8, 0x00, 0x00];
+    rerun(cpu, &ops);
+}
+
+/// load fresh system opcodes and run them from the start, on a CPU that may have already halted
+#[cfg(test)]
+fn rerun(cpu: &mut CPU, ops: &[u8]) {
+    cpu.write_system_mem(ops).unwrap();
+    cpu.pc = 0;
+    cpu.halted = false;
+    cpu.run().unwrap();
+}
+
+/// collect the coordinates of every lit pixel
+#[cfg(test)]
+fn lit_pixels(cpu: &CPU) -> Vec<(usize, usize)> {
+    (0..cpu.framebuffer().len())
+        .filter(|idx| cpu.framebuffer()[*idx])
+        .map(|idx| (idx % CPU::DISPLAY_WIDTH, idx / CPU::DISPLAY_WIDTH))
+        .collect()
+}
+
+#[test]
+pub fn test_draw_sprite() {
+    let mut cpu = CPU::new();
+    draw_sprite(&mut cpu, &[0b1100_0000, 0b0100_0000], 2, 3);
+
+    assert_eq!(lit_pixels(&cpu), [(2, 3), (3, 3), (3, 4)]);
+    assert_eq!(cpu.reg[0xF], 0);
+}
+
+#[test]
+pub fn test_draw_sprite_twice_clears_it() {
+    let mut cpu = CPU::new();
+    let sprite = [0xF0, 0x90, 0xF0];
+
+    draw_sprite(&mut cpu, &sprite, 10, 10);
+    assert_eq!(lit_pixels(&cpu).len(), 10);
+    assert_eq!(cpu.reg[0xF], 0);
+
+    draw_sprite(&mut cpu, &sprite, 10, 10);
+    assert!(lit_pixels(&cpu).is_empty());
+    assert_eq!(cpu.reg[0xF], 1);
+}
+
+#[test]
+pub fn test_draw_overlapping_sprites() {
+    let mut cpu = CPU::new();
+
+    // two horizontal bars overlapping by a single pixel at x=3
+    draw_sprite(&mut cpu, &[0b1111_0000], 0, 0);
+    draw_sprite(&mut cpu, &[0b1111_0000], 3, 0);
+    assert_eq!(cpu.reg[0xF], 1);
+    assert_eq!(
+        lit_pixels(&cpu),
+        [(0, 0), (1, 0), (2, 0), (4, 0), (5, 0), (6, 0)]
+    );
+
+    // a sprite landing only on unlit pixels clears the collision flag
+    draw_sprite(&mut cpu, &[0b1111_0000], 0, 1);
+    assert_eq!(cpu.reg[0xF], 0);
+}
+
+#[test]
+pub fn test_draw_collision_clobbers_vf() {
+    let mut cpu = CPU::new();
+    draw_sprite(&mut cpu, &[0x80], 0, 0);
+
+    // drawing at (VF, VF) still leaves the collision flag in VF
+    cpu.reg[0xF] = 0;
+    rerun(&mut cpu, &[0xA2, 0x00, 0xDF, 0xF1, 0x00, 0x00]);
+    assert_eq!(cpu.reg[0xF], 1);
+    assert!(lit_pixels(&cpu).is_empty());
+}
+
+#[test]
+pub fn test_draw_wraps_start_and_clips_body() {
+    let mut cpu = CPU::new();
+
+    // the start coordinate wraps: (66, 33) lands at (2, 1)
+    draw_sprite(&mut cpu, &[0x80], 66, 33);
+    assert_eq!(lit_pixels(&cpu), [(2, 1)]);
+
+    // but by default the body is clipped at the display edges
+    let mut cpu = CPU::new();
+    draw_sprite(&mut cpu, &[0xFF, 0xFF], 60, 31);
+    assert_eq!(lit_pixels(&cpu), [(60, 31), (61, 31), (62, 31), (63, 31)]);
+}
+
+#[test]
+pub fn test_draw_wrap_quirk() {
+    let mut cpu = CPU::builder()
+        .with_quirks(Quirks { wrap_sprites: true })
+        .build();
+    draw_sprite(&mut cpu, &[0b1100_0000, 0b1100_0000], 63, 31);
+
+    assert_eq!(lit_pixels(&cpu), [(0, 0), (63, 0), (0, 31), (63, 31)]);
+}
+
+#[test]
+pub fn test_clear_display() {
+    let mut cpu = CPU::new();
+    draw_sprite(&mut cpu, &[0xFF], 0, 0);
+
+    rerun(&mut cpu, &[0x00, 0xE0, 0x00, 0x00]);
+    assert!(lit_pixels(&cpu).is_empty());
+}
+
+/// execute a single ALU opcode against the given registers
+#[cfg(test)]
+fn run_alu(reg: &[(usize, u8)], opcode: u16) -> CPU {
+    let mut cpu = CPU::new();
+    for (idx, val) in reg {
+        cpu.reg[*idx] = *val;
+    }
+    cpu.write_system_mem(&[(opcode >> 8) as u8, opcode as u8, 0x00, 0x00])
+        .unwrap();
+    cpu.run().unwrap();
+    cpu
+}
+
+#[test]
+pub fn test_sub_borrow_flag() {
+    // no borrow: VF = 1
+    let cpu = run_alu(&[(0, 10), (1, 3)], 0x8015);
+    assert_eq!((cpu.reg[0], cpu.reg[0xF]), (7, 1));
+
+    // equal operands don't borrow either
+    let cpu = run_alu(&[(0, 10), (1, 10)], 0x8015);
+    assert_eq!((cpu.reg[0], cpu.reg[0xF]), (0, 1));
+
+    // underflow wraps and borrows: VF = 0
+    let cpu = run_alu(&[(0, 3), (1, 10)], 0x8015);
+    assert_eq!((cpu.reg[0], cpu.reg[0xF]), (249, 0));
+}
+
+#[test]
+pub fn test_subn_borrow_flag() {
+    // Vx = Vy - Vx
+    let cpu = run_alu(&[(0, 3), (1, 10)], 0x8017);
+    assert_eq!((cpu.reg[0], cpu.reg[0xF]), (7, 1));
+
+    let cpu = run_alu(&[(0, 10), (1, 10)], 0x8017);
+    assert_eq!((cpu.reg[0], cpu.reg[0xF]), (0, 1));
+
+    let cpu = run_alu(&[(0, 10), (1, 3)], 0x8017);
+    assert_eq!((cpu.reg[0], cpu.reg[0xF]), (249, 0));
+}
+
+#[test]
+pub fn test_sub_flag_register_aliasing() {
+    // Vx is VF: the flag is computed from the original operands and written last
+    let cpu = run_alu(&[(0xF, 3), (1, 10)], 0x8F15);
+    assert_eq!(cpu.reg[0xF], 0);
+    let cpu = run_alu(&[(0xF, 10), (1, 3)], 0x8F15);
+    assert_eq!(cpu.reg[0xF], 1);
+
+    // Vy is VF: the result uses VF's value from before the flag write
+    let cpu = run_alu(&[(0, 10), (0xF, 3)], 0x80F5);
+    assert_eq!((cpu.reg[0], cpu.reg[0xF]), (7, 1));
+    let cpu = run_alu(&[(0, 3), (0xF, 10)], 0x80F7);
+    assert_eq!((cpu.reg[0], cpu.reg[0xF]), (7, 1));
+}
+
+#[test]
+pub fn test_timer_opcodes() {
+    // DT = V0, ST = V1, V2 = DT
+    let mut cpu = CPU::new();
+    (cpu.reg[0], cpu.reg[1]) = (5, 7);
+    cpu.write_system_mem(&[0xF0, 0x15, 0xF1, 0x18, 0xF2, 0x07, 0x00, 0x00])
+        .unwrap();
+    cpu.run().unwrap();
+    assert_eq!(
+        (cpu.delay_timer(), cpu.sound_timer(), cpu.reg[2]),
+        (5, 7, 5)
+    );
+
+    // timers stop at zero
+    for _ in 0..6 {
+        cpu.tick_timers();
+    }
+    assert_eq!((cpu.delay_timer(), cpu.sound_timer()), (0, 1));
+}
+
+#[test]
+pub fn test_tick_elapsed_time() {
+    let mut cpu = CPU::new();
+    (cpu.delay_timer, cpu.sound_timer) = (10, 10);
+
+    // 1/30th of a second is two 60Hz ticks
+    cpu.tick(Duration::from_secs_f64(1.0 / 30.0));
+    assert_eq!((cpu.delay_timer(), cpu.sound_timer()), (8, 8));
+
+    // fractions of a tick accumulate
+    cpu.tick(Duration::from_millis(10));
+    assert_eq!(cpu.delay_timer(), 8);
+    cpu.tick(Duration::from_millis(10));
+    assert_eq!(cpu.delay_timer(), 7);
+}
+
+#[test]
+pub fn test_halt_state() {
+    let mut cpu = CPU::new();
+    cpu.reg[1] = 1;
+    cpu.write_system_mem(&[0x80, 0x14, 0x00, 0x00]).unwrap();
+
+    assert!(!cpu.is_halted());
+    assert_eq!(cpu.step(), Ok(StepOutcome::Executed));
+    assert!(!cpu.is_halted());
+    assert_eq!(cpu.step(), Ok(StepOutcome::Halted));
+    assert!(cpu.is_halted());
+
+    // stepping a halted CPU makes no further progress
+    let pc = cpu.pc;
+    assert_eq!(cpu.step(), Ok(StepOutcome::Halted));
+    assert_eq!((cpu.pc, cpu.reg[0]), (pc, 1));
+}
+
+#[test]
+pub fn test_pause_and_resume() {
+    let mut cpu = CPU::new();
+    cpu.reg[1] = 1;
+    cpu.write_system_mem(&[0x80, 0x14, 0x80, 0x14, 0x00, 0x00])
+        .unwrap();
+
+    assert_eq!(cpu.step(), Ok(StepOutcome::Executed));
+    cpu.pause();
+    assert!(cpu.is_paused());
+
+    // no progress is made while paused
+    assert_eq!(cpu.step(), Ok(StepOutcome::Paused));
+    cpu.run().unwrap();
+    assert_eq!((cpu.pc, cpu.reg[0]), (2, 1));
+
+    cpu.resume();
+    cpu.run().unwrap();
+    assert_eq!(cpu.reg[0], 2);
+    assert!(cpu.is_halted() && !cpu.is_paused());
+}
+
+#[test]
+pub fn test_timers_while_paused() {
+    let mut cpu = CPU::new();
+    cpu.delay_timer = 10;
+    cpu.pause();
+
+    // timers are frozen while paused, unless asked otherwise
+    cpu.tick(CPU::TIMER_PERIOD);
+    assert_eq!(cpu.delay_timer(), 10);
+    cpu.set_tick_while_paused(true);
+    cpu.tick(CPU::TIMER_PERIOD);
+    assert_eq!(cpu.delay_timer(), 9);
+}
+
+#[test]
+pub fn test_call_frames() {
+    let mut cpu = CPU::new();
+    assert_eq!(cpu.call_depth(), 0);
+    assert!(cpu.call_frames().is_empty());
+
+    cpu.pc = 0x002;
+    cpu.call(0x100).unwrap();
+    cpu.pc = 0x106;
+    cpu.call(0x200).unwrap();
+    assert_eq!(cpu.call_depth(), 2);
+    assert_eq!(cpu.call_frames(), [0x002, 0x106]);
+
+    cpu.ret().unwrap();
+    assert_eq!(cpu.call_depth(), 1);
+    assert_eq!(cpu.call_frames(), [0x002]);
+}
+
+#[test]
+pub fn test_builder() {
+    let build = || {
+        CPU::builder()
+            .with_quirks(Quirks { wrap_sprites: true })
+            .with_rng_seed(42)
+            .with_memory_size(0x800)
+            .with_program_start(0x200)
+            .with_stack_depth(2)
+            .build()
+    };
+    let mut cpu = build();
+    assert_eq!(cpu.quirks, Quirks { wrap_sprites: true });
+    assert_eq!(cpu.program_start(), 0x200);
+    assert_eq!(cpu.mem_slice(0x7FF..0x800), Some(&[0x00][..]));
+    assert_eq!(cpu.mem_slice(0x7FF..0x801), None);
+    assert!(cpu.write_system_mem(&[0x00; 0x200]).is_ok());
+    assert_eq!(
+        cpu.write_system_mem(&[0x00; 0x201]),
+        Err(Chip8Error::SystemMemoryOverflow {
+            len: 0x201,
+            size: 0x200
+        })
+    );
+
+    // programs load at (and run from a jump to) the configured start
+    cpu.write_system_mem(&[0x12, 0x00]).unwrap(); // JP 0x200
+    cpu.write_prog_mem(&[0xC0, 0xFF, 0x00, 0x00]).unwrap(); // V0 = random & 0xFF
+    cpu.run().unwrap();
+    let mut other = build();
+    other.write_system_mem(&[0xC0, 0xFF, 0x00, 0x00]).unwrap();
+    other.run().unwrap();
+    assert_eq!(cpu.reg[0], other.reg[0]);
+
+    cpu.call(0x200).unwrap();
+    cpu.call(0x200).unwrap();
+    assert_eq!(cpu.call(0x200), Err(Chip8Error::StackOverflow { depth: 2 }));
+
+    // running off the end of a small memory is an error, even partway through an opcode
+    for size in [0x104, 0x105] {
+        let mut cpu = CPU::builder().with_memory_size(size).build();
+        cpu.write_system_mem(&[0x11, 0x00]).unwrap(); // JP 0x100
+        cpu.write_prog_mem(&[0x60, 0x01, 0x61, 0x02]).unwrap(); // LD V0, 1; LD V1, 2
+        assert_eq!(cpu.run(), Err(Chip8Error::PcOutOfBounds { pc: 0x104 }));
+        assert_eq!(cpu.reg[..2], [0x01, 0x02]);
+    }
+
+    // anything left unconfigured takes the default
+    let cpu = CPU::builder().build();
+    assert_eq!(cpu.program_start(), CPU::new().program_start());
+    assert_eq!(cpu.mem.len(), CPU::DEFAULT_MEMORY_SIZE);
+    assert_eq!(cpu.stack.len(), CPU::DEFAULT_STACK_DEPTH);
+}
+
+#[test]
+pub fn test_stack_depth() {
+    let mut cpu = CPU::builder().with_stack_depth(4).build();
+    for _ in 0..4 {
+        cpu.call(0x100).unwrap();
+    }
+    assert_eq!(cpu.call_depth(), 4);
+    assert_eq!(cpu.call(0x100), Err(Chip8Error::StackOverflow { depth: 4 }));
+
+    // the default supports 16 nested calls
+    let mut cpu = CPU::new();
+    for _ in 0..16 {
+        cpu.call(0x100).unwrap();
+    }
+    assert_eq!(
+        cpu.call(0x100),
+        Err(Chip8Error::StackOverflow { depth: 16 })
+    );
+}
+
+#[test]
+pub fn test_stack_underflow_and_unknown_opcode() {
+    let mut cpu = CPU::new();
+    assert_eq!(cpu.ret(), Err(Chip8Error::StackUnderflow));
+
+    cpu.write_system_mem(&[0x80, 0x14, 0xFF, 0xFF]).unwrap();
+    assert_eq!(
+        cpu.run(),
+        Err(Chip8Error::UnknownOpcode {
+            addr: 0x002,
+            opcode: 0xFFFF
+        })
+    );
+}
+
+#[test]
+pub fn test_pc_out_of_bounds() {
+    let mut cpu = CPU::new();
+    cpu.write_system_mem(&[0x1F, 0xFF]).unwrap(); // JP 0xFFF
+    assert_eq!(cpu.run(), Err(Chip8Error::PcOutOfBounds { pc: 0xFFF }));
+    assert_eq!(
+        cpu.next_opcode(),
+        Err(Chip8Error::PcOutOfBounds { pc: 0xFFF })
+    );
+
+    // falling through the last opcode in memory runs off the end too
+    let mut cpu = CPU::new();
+    cpu.write_system_mem(&[0x1F, 0xFE]).unwrap(); // JP 0xFFE
+    cpu.write_at(0xFFE, &[0x60, 0x01]).unwrap(); // LD V0, 1
+    let steps: Vec<_> = cpu.instructions().collect();
+    assert_eq!(
+        steps,
+        [
+            Ok((0x000, 0x1FFE)),
+            Ok((0xFFE, 0x6001)),
+            Err(Chip8Error::PcOutOfBounds { pc: 0x1000 })
+        ]
+    );
+}
+
+#[test]
+pub fn test_program_len() {
+    let mut cpu = CPU::new();
+    assert_eq!(cpu.program_len(), 0);
+
+    cpu.write_prog_mem(&[0x80, 0x14, 0x80, 0x14, 0x00, 0xEE])
+        .unwrap();
+    assert_eq!(cpu.program_len(), 6);
+
+    // reloading replaces the previous program
+    cpu.write_prog_mem(&[0x00, 0xEE]).unwrap();
+    assert_eq!(cpu.program_len(), 2);
+}
+
+#[test]
+pub fn test_write_at() {
+    let mut cpu = CPU::new();
+    cpu.write_at(0x800, &[0xDE, 0xAD]).unwrap();
+    assert_eq!(cpu.mem[0x7FF..0x803], [0x00, 0xDE, 0xAD, 0x00]);
+
+    // the final byte of memory can be written, but nothing beyond it
+    cpu.write_at(0xFFF, &[0x01]).unwrap();
+    assert_eq!(
+        cpu.write_at(0xFFF, &[0x01, 0x02]),
+        Err(Chip8Error::MemoryOverflow {
+            addr: 0xFFF,
+            len: 2,
+            size: 4096
+        })
+    );
+    assert!(cpu.write_at(usize::MAX, &[0x01]).is_err());
+
+    // oversized programs are rejected rather than partially loaded
+    assert!(cpu.write_prog_mem(&[0xFF; 4096]).is_err());
+    assert_eq!(cpu.program_len(), 0);
+}
+
+#[test]
+pub fn test_rom_hash() {
+    let mut cpu = CPU::new();
+    assert_eq!(cpu.rom_hash(), 0xcbf29ce484222325); // nothing loaded
+
+    // FNV-1a's reference test vector
+    cpu.write_prog_mem(b"a").unwrap();
+    assert_eq!(cpu.rom_hash(), 0xaf63dc4c8601ec8c);
+
+    cpu.write_prog_mem(&[0x80, 0x14, 0x80, 0x14, 0x00, 0xEE])
+        .unwrap();
+    assert_eq!(cpu.rom_hash(), 0xd001894ad54b63f3);
+
+    // only the program region counts
+    cpu.write_system_mem(&[0x21, 0x00]).unwrap();
+    assert_eq!(cpu.rom_hash(), 0xd001894ad54b63f3);
+}
+
+#[test]
+pub fn test_mem_diff() {
+    let mut cpu = CPU::new();
+    cpu.write_prog_mem(&[0x80, 0x14, 0x00, 0xEE]).unwrap();
+    let mut patched = cpu.clone();
+    assert!(cpu.mem_diff(&patched).is_empty());
+
+    patched.write_at(0x101, &[0x24]).unwrap();
+    assert_eq!(cpu.mem_diff(&patched), [(0x101, 0x14, 0x24)]);
+    assert_eq!(patched.mem_diff(&cpu), [(0x101, 0x24, 0x14)]);
+}
+
+#[test]
+pub fn test_run_until() {
+    // test_call_and_return's program
+    let mut cpu = CPU::new();
+    (cpu.reg[0], cpu.reg[1]) = (5, 10);
+    cpu.write_system_mem(&[0x21, 0x00, 0x21, 0x00, 0x00, 0x00])
+        .unwrap();
+    cpu.write_prog_mem(&[0x80, 0x14, 0x80, 0x14, 0x00, 0xEE])
+        .unwrap();
+
+    // stop ahead of the first call's second ADD
+    assert_eq!(cpu.run_until(0x102, 100), Ok(StepOutcome::TargetReached));
+    assert_eq!((cpu.pc, cpu.reg[0], cpu.call_depth()), (0x102, 15, 1));
+
+    // already at the target, so nothing is executed
+    assert_eq!(cpu.run_until(0x102, 100), Ok(StepOutcome::TargetReached));
+    assert_eq!(cpu.reg[0], 15);
+
+    // the budget runs out first
+    assert_eq!(cpu.run_until(0x002, 1), Ok(StepOutcome::CycleLimit));
+    assert_eq!(cpu.reg[0], 25);
+
+    // the program halts without ever reaching the target
+    assert_eq!(cpu.run_until(0x800, 100), Ok(StepOutcome::Halted));
+    assert_eq!(cpu.reg[0], 45);
+}
+
+#[test]
+pub fn test_jump_skip_and_load_immediate() {
+    let mut cpu = CPU::new();
+    cpu.write_system_mem(&[
+        0x60, 0x00, // 0x000: V0 = 0
+        0x70, 0x01, // 0x002: V0 += 1
+        0x30, 0x05, // 0x004: skip the next opcode if V0 == 5
+        0x10, 0x02, // 0x006: jump to 0x002
+        0x00, 0x00, // 0x008: halt
+    ])
+    .unwrap();
+    cpu.run().unwrap();
+    assert_eq!(cpu.reg[0], 5);
+
+    // adding an immediate wraps without touching VF
+    let cpu = run_alu(&[(0, 0xFF), (0xF, 7)], 0x7002);
+    assert_eq!((cpu.reg[0], cpu.reg[0xF]), (1, 7));
+}
+
+/// a loop incrementing V0 from 0 up to 5
+#[cfg(test)]
+const COUNT_TO_FIVE: [u8; 10] = [0x60, 0x00, 0x70, 0x01, 0x30, 0x05, 0x10, 0x02, 0x00, 0x00];
+
+#[test]
+pub fn test_breakpoints() {
+    let mut cpu = CPU::new();
+    cpu.write_system_mem(&COUNT_TO_FIVE).unwrap();
+    cpu.add_breakpoint(0x004);
+
+    // each run stops ahead of the breakpoint, once per iteration of the loop
+    for expected in 1..=5 {
+        cpu.run().unwrap();
+        assert_eq!((cpu.pc, cpu.reg[0]), (0x004, expected));
+    }
+    cpu.run().unwrap();
+    assert!(cpu.is_halted());
+
+    let mut cpu = CPU::new();
+    cpu.write_system_mem(&COUNT_TO_FIVE).unwrap();
+    cpu.add_breakpoint(0x004);
+    cpu.remove_breakpoint(0x004);
+    cpu.run().unwrap();
+    assert!(cpu.is_halted());
+}
+
+#[test]
+pub fn test_conditional_breakpoint() {
+    let mut cpu = CPU::new();
+    cpu.write_system_mem(&COUNT_TO_FIVE).unwrap();
+    cpu.add_conditional_breakpoint(0x004, 0, 3).unwrap();
+    assert_eq!(
+        cpu.add_conditional_breakpoint(0x004, 0x13, 3),
+        Err(Chip8Error::InvalidRegister { idx: 0x13 })
+    );
+
+    // only the third iteration of the loop matches
+    assert_eq!(cpu.run_until(0x800, 100), Ok(StepOutcome::Breakpoint));
+    assert_eq!((cpu.pc, cpu.reg[0]), (0x004, 3));
+
+    assert_eq!(cpu.run_until(0x800, 100), Ok(StepOutcome::Halted));
+    assert_eq!(cpu.reg[0], 5);
+}
+
+#[test]
+pub fn test_patch_running_program() {
+    let mut cpu = CPU::new();
+    cpu.reg[1] = 1;
+    cpu.write_system_mem(&[
+        0x80, 0x14, // 0x000: V0 += V1
+        0x10, 0x00, // 0x002: jump to 0x000 (forever)
+    ])
+    .unwrap();
+    assert_eq!(cpu.run_until(0x800, 100), Ok(StepOutcome::CycleLimit));
+    assert_eq!(cpu.reg[0], 50);
+
+    // swap the jump for a halt, and the loop ends
+    cpu.patch(0x002, 0x0000).unwrap();
+    assert_eq!(cpu.run_until(0x800, 100), Ok(StepOutcome::Halted));
+    assert_eq!(cpu.reg[0], 51);
+
+    assert!(cpu.patch(0xFFF, 0x1234).is_err());
+}
+
+#[test]
+pub fn test_profile_report() {
+    let mut cpu = CPU::new();
+    assert!(cpu.profile_report().is_empty());
+
+    cpu.write_system_mem(&COUNT_TO_FIVE).unwrap();
+    cpu.run().unwrap();
+    assert_eq!(
+        cpu.profile_report(),
+        [
+            ("ADD V0, 0x01".to_string(), 5),
+            ("SE V0, 0x05".to_string(), 5),
+            ("JP 0x002".to_string(), 4),
+            ("HALT".to_string(), 1),
+            ("LD V0, 0x00".to_string(), 1),
+        ]
+    );
+}
+
+#[test]
+pub fn test_random_is_seeded() {
+    // V0..V3 = random & 0xFF
+    let program = [0xC0, 0xFF, 0xC1, 0xFF, 0xC2, 0xFF, 0xC3, 0xFF, 0x00, 0x00];
+    let run = |mut cpu: CPU| {
+        cpu.write_system_mem(&program).unwrap();
+        cpu.run().unwrap();
+        cpu.reg
+    };
+
+    assert_eq!(
+        run(CPU::builder().with_rng_seed(42).build()),
+        run(CPU::builder().with_rng_seed(42).build())
+    );
+    assert_ne!(
+        run(CPU::builder().with_rng_seed(42).build()),
+        run(CPU::builder().with_rng_seed(43).build())
+    );
+    assert_eq!(
+        run(CPU::new()),
+        run(CPU::builder().with_rng_seed(CPU::DEFAULT_RNG_SEED).build())
+    );
+
+    // the random byte is masked by kk
+    let mut cpu = CPU::new();
+    cpu.write_system_mem(&[0xC0, 0x0F, 0x00, 0x00]).unwrap();
+    cpu.run().unwrap();
+    assert_eq!(cpu.reg[0] & 0xF0, 0);
+}
+
+#[test]
+pub fn test_run_for() {
+    // jump to self, forever
+    let mut cpu = CPU::new();
+    cpu.write_system_mem(&[0x10, 0x00]).unwrap();
+    assert_eq!(cpu.run_for(100), Ok(StepOutcome::CycleLimit));
+    assert_eq!(cpu.profile_report(), [("JP 0x000".to_string(), 100)]);
+
+    // a program that finishes within the budget halts as normal
+    let mut cpu = CPU::new();
+    cpu.write_system_mem(&[0x70, 0x01, 0x70, 0x01, 0x00, 0x00])
+        .unwrap();
+    assert_eq!(cpu.run_for(100), Ok(StepOutcome::Halted));
+    assert_eq!(cpu.reg[0], 2);
+
+    // breakpoints stop the run, like they do for run()
+    let mut cpu = CPU::new();
+    cpu.write_system_mem(&COUNT_TO_FIVE).unwrap();
+    cpu.add_breakpoint(0x004);
+    assert_eq!(cpu.run_for(100), Ok(StepOutcome::Breakpoint));
+    assert_eq!(cpu.reg[0], 1);
+}
+
+#[test]
+pub fn test_render_ascii() {
+    let mut cpu = CPU::new();
+    draw_sprite(&mut cpu, &[0b1010_0000, 0b0101_0000], 1, 0);
+
+    let text = cpu.render_ascii();
+    let rows: Vec<&str> = text.lines().collect();
+    assert_eq!(rows.len(), CPU::DISPLAY_HEIGHT);
+    assert!(rows.iter().all(|row| row.len() == CPU::DISPLAY_WIDTH));
+    assert_eq!(rows[0].trim_end(), " # #");
+    assert_eq!(rows[1].trim_end(), "  # #");
+    assert!(rows[2..].iter().all(|row| row.trim().is_empty()));
+}
+
+#[test]
+pub fn test_beep_frequency() {
+    use std::{cell::RefCell, rc::Rc};
+
+    let mut cpu = CPU::new();
+    assert_eq!(cpu.beep_frequency(), CPU::DEFAULT_BEEP_HZ);
+    assert_eq!(
+        cpu.set_beep_frequency(5.0),
+        Err(Chip8Error::InaudibleFrequency { hz: 5.0 })
+    );
+    assert!(cpu.set_beep_frequency(f32::NAN).is_err());
+    cpu.set_beep_frequency(880.0).unwrap();
+
+    let tones = Rc::new(RefCell::new(Vec::new()));
+    let played = Rc::clone(&tones);
+    cpu.set_audio_hook(Box::new(move |tone| played.borrow_mut().push(tone)));
+
+    // beep for two ticks, then fall silent
+    cpu.reg[0] = 2;
+    cpu.write_system_mem(&[0xF0, 0x18, 0x00, 0x00]).unwrap();
+    cpu.run().unwrap();
+    for _ in 0..3 {
+        cpu.tick_timers();
+    }
+    assert_eq!(*tones.borrow(), [Some(880.0), Some(880.0), None]);
+}
+
+#[test]
+pub fn test_framebuffer_hash() {
+    let mut cpu = CPU::new();
+    let blank = cpu.framebuffer_hash();
+
+    draw_sprite(&mut cpu, &[0x80], 0, 0);
+    let drawn = cpu.framebuffer_hash();
+    assert_ne!(drawn, blank);
+
+    // the same pixels always give the same hash
+    let mut other = CPU::new();
+    draw_sprite(&mut other, &[0x80], 0, 0);
+    assert_eq!(other.framebuffer_hash(), drawn);
+
+    rerun(&mut cpu, &[0x00, 0xE0, 0x00, 0x00]); // CLS
+    assert_eq!(cpu.framebuffer_hash(), blank);
+}
+
+#[test]
+pub fn test_instructions() {
+    // test_addition's program
+    let mut cpu = CPU::new();
+    cpu.reg[..4].copy_from_slice(&[5, 10, 10, 10]);
+    cpu.write_system_mem(&[0x80, 0x14, 0x80, 0x24, 0x80, 0x34, 0x00, 0x00])
+        .unwrap();
+
+    let executed: Vec<(usize, u16)> = cpu.instructions().map(Result::unwrap).collect();
+    assert_eq!(
+        executed,
+        [
+            (0x000, 0x8014),
+            (0x002, 0x8024),
+            (0x004, 0x8034),
+            (0x006, 0x0000)
+        ]
+    );
+    assert_eq!(cpu.reg[0], 35);
+    assert_eq!(cpu.instructions().count(), 0); // already halted
+
+    // composes with the usual adapters, stopping wherever the caller likes
+    let mut cpu = CPU::new();
+    cpu.write_system_mem(&COUNT_TO_FIVE).unwrap();
+    let adds = cpu
+        .instructions()
+        .filter_map(Result::ok)
+        .filter(|(_, opcode)| opcode >> 12 == 0x7)
+        .take(3)
+        .count();
+    assert_eq!((adds, cpu.reg[0]), (3, 3));
+
+    // errors end the iteration
+    let mut cpu = CPU::new();
+    cpu.write_system_mem(&[0x70, 0x01, 0xFF, 0xFF, 0x00, 0x00])
+        .unwrap();
+    let results: Vec<_> = cpu.instructions().collect();
+    assert_eq!(results.len(), 2);
+    assert!(results[1].is_err());
+}
+
+#[test]
+pub fn test_operands() {
+    assert_eq!(
+        operands(0x73EE),
+        Operands {
+            x: 0x3,
+            y: 0xE,
+            n: 0xE,
+            kk: 0xEE,
+            nnn: 0x3EE
+        }
+    );
+    let Operands { x, y, n, .. } = operands(0xD015);
+    assert_eq!((x, y, n), (0x0, 0x1, 0x5));
+}
+
+#[test]
+pub fn test_opcode_masks() {
+    let opcode = 0xD12A;
+    assert_eq!(opcode & OPCODE_GROUP_MASK, 0xD000);
+    assert_eq!(opcode & X_MASK, 0x0100);
+    assert_eq!(opcode & Y_MASK, 0x0020);
+    assert_eq!(opcode & N_MASK, 0x000A);
+    assert_eq!(opcode & KK_MASK, 0x002A);
+    assert_eq!(opcode & NNN_MASK, 0x012A);
+
+    // the group and address together make up the whole opcode
+    assert_eq!(OPCODE_GROUP_MASK | NNN_MASK, 0xFFFF);
+    assert_eq!(X_MASK | KK_MASK, NNN_MASK);
+}
+
+#[test]
+pub fn test_write_system_mem_overflow() {
+    let mut cpu = CPU::new();
+    assert!(cpu.write_system_mem(&[0xFF; 0x100]).is_ok());
+    assert_eq!(
+        cpu.write_system_mem(&[0xFF; 0x101]),
+        Err(Chip8Error::SystemMemoryOverflow {
+            len: 0x101,
+            size: 0x100
+        })
+    );
+    // nothing spills over into program memory
+    assert_eq!(cpu.mem[0x100], 0x00);
+}
+
+#[test]
+pub fn test_font() {
+    assert_eq!(FONT_SET.len(), 80);
+    assert_eq!(font_address(0x0), FONT_START);
+    assert_eq!(font_address(0xF), FONT_START + 75);
+    assert_eq!(font_address(0x1F), font_address(0xF));
+
+    let cpu = CPU::new();
+    let zero = font_address(0);
+    assert_eq!(cpu.mem[zero..zero + 5], [0xF0, 0x90, 0x90, 0x90, 0xF0]);
+
+    // FX29 points I at the digit in VX, ready to be drawn
+    let mut cpu = CPU::new();
+    cpu.reg[3] = 0x8;
+    cpu.write_system_mem(&[0xF3, 0x29, 0xD0, 0x15, 0x00, 0x00])
+        .unwrap();
+    cpu.run().unwrap();
+    assert_eq!(cpu.i as usize, font_address(0x8));
+    assert_eq!(
+        cpu.render_ascii().lines().take(5).collect::<Vec<_>>(),
+        ["####", "#  #", "####", "#  #", "####"].map(|row| format!("{:<64}", row))
+    );
+}
+
+#[test]
+pub fn test_bcd() {
+    let vectors = [
+        (0, [0, 0, 0]),
+        (9, [0, 0, 9]),
+        (10, [0, 1, 0]),
+        (99, [0, 9, 9]),
+        (100, [1, 0, 0]),
+        (128, [1, 2, 8]),
+        (255, [2, 5, 5]),
+    ];
+    for (val, digits) in vectors {
+        let mut cpu = CPU::new();
+        cpu.mem[0x300..0x303].fill(0xFF); // so a missing hundreds digit would show
+        cpu.reg[0xA] = val;
+        cpu.write_system_mem(&[0xA3, 0x00, 0xFA, 0x33, 0x00, 0x00])
+            .unwrap();
+        cpu.run().unwrap();
+        assert_eq!(cpu.mem[0x300..0x303], digits, "BCD of {}", val);
+        assert_eq!(cpu.i, 0x300); // I is left unchanged
+    }
+
+    // digits that would run off the end of memory are an error, not a panic
+    let mut cpu = CPU::new();
+    cpu.write_system_mem(&[0xAF, 0xFE, 0xF0, 0x33]).unwrap();
+    assert!(matches!(
+        cpu.run(),
+        Err(Chip8Error::MemoryOverflow { addr: 0xFFE, .. })
+    ));
+}
+
+#[test]
+pub fn test_load_program_byte_order() {
+    let mut cpu = CPU::new();
+    cpu.load_program(&[0x14, 0x80, 0xEE, 0x00], Endianness::Little)
+        .unwrap();
+    cpu.pc = CPU::RES_SYS_MEM;
+    assert_eq!(cpu.read_opcode(), Ok(0x8014));
+    cpu.pc += 2;
+    assert_eq!(cpu.read_opcode(), Ok(0x00EE));
+    assert_eq!(cpu.program_len(), 4);
+
+    // big-endian programs are loaded as-is
+    let mut other = CPU::new();
+    other
+        .load_program(&[0x80, 0x14, 0x00, 0xEE], Endianness::Big)
+        .unwrap();
+    assert!(cpu.mem_diff(&other).is_empty());
+
+    assert_eq!(
+        cpu.load_program(&[0x14, 0x80, 0xEE], Endianness::Little),
+        Err(Chip8Error::OddProgramLength { len: 3 })
+    );
+}
+
+#[test]
+pub fn test_disassemble_program() {
+    // test_call_and_return's program
+    let mut cpu = CPU::new();
+    cpu.write_system_mem(&[0x21, 0x00, 0x21, 0x00, 0x00, 0x00])
+        .unwrap();
+    cpu.write_prog_mem(&[0x80, 0x14, 0x80, 0x14, 0x00, 0xEE])
+        .unwrap();
+    assert_eq!(
+        cpu.disassemble_program(),
+        [
+            (0x100, 0x8014, "ADD V0, V1".to_string()),
+            (0x102, 0x8014, "ADD V0, V1".to_string()),
+            (0x104, 0x00EE, "RET".to_string()),
+        ]
+    );
+
+    cpu.write_prog_mem(&[0x00, 0xE0, 0x12]).unwrap();
+    let rows = cpu.disassemble_program();
+    assert_eq!(rows[1], (0x102, 0x12, "DB 0x12".to_string()));
+}
+
+#[test]
+pub fn test_disassemble_program_marks_sprites_as_data() {
+    let mut cpu = CPU::new();
+    let program = crate::asm::assemble(
+        "
+        LD I, arrow
+        DRW V0, V1, 3
+        HALT
+        arrow:
+            SPRITE ..#.....
+            SPRITE .###....
+            SPRITE #####...
+    ",
+    )
+    .unwrap();
+    cpu.write_prog_mem(program.bytes()).unwrap();
+    assert_eq!(
+        cpu.disassemble_program(),
+        [
+            (0x100, 0xA106, "LD I, 0x106".to_string()),
+            (0x102, 0xD013, "DRW V0, V1, 3".to_string()),
+            (0x104, 0x0000, "HALT".to_string()),
+            (0x106, 0x20, "DB 0x20".to_string()),
+            (0x107, 0x70, "DB 0x70".to_string()),
+            (0x108, 0xF8, "DB 0xF8".to_string()),
+        ]
+    );
+}
+
+#[test]
+pub fn test_mem_slice() {
+    let mut cpu = CPU::new();
+    cpu.write_prog_mem(&[0x80, 0x14, 0x00, 0xEE]).unwrap();
+    assert_eq!(
+        cpu.mem_slice(0x100..0x104),
+        Some(&[0x80, 0x14, 0x00, 0xEE][..])
+    );
+    assert_eq!(cpu.mem_slice(0xFFF..0x1000), Some(&[0x00][..]));
+    assert_eq!(cpu.mem_slice(0x100..0x100), Some(&[][..]));
+
+    assert_eq!(cpu.mem_slice(0xFFF..0x1001), None);
+    assert_eq!(cpu.mem_slice(0x2000..0x2004), None);
+}
+
+#[test]
+pub fn test_set_and_get_reg() {
+    let mut cpu = CPU::new();
+    cpu.set_reg(0xA, 42).unwrap();
+    assert_eq!(cpu.get_reg(0xA), Ok(42));
+    assert_eq!(cpu.reg[0xA], 42);
+    cpu.set_reg(0xF, 1).unwrap();
+    assert_eq!(cpu.get_reg(0xF), Ok(1));
+
+    assert_eq!(
+        cpu.set_reg(16, 42),
+        Err(Chip8Error::InvalidRegister { idx: 16 })
+    );
+    assert_eq!(
+        cpu.get_reg(255),
+        Err(Chip8Error::InvalidRegister { idx: 255 })
+    );
+}
+
+#[test]
+pub fn test_alu_flags() {
+    // (registers, opcode, expected Vx, expected VF)
+    let cases = [
+        (&[(0, 200), (1, 100)][..], 0x8014, 44, 1), // ADD: carry
+        (&[(0, 20), (1, 100)][..], 0x8014, 120, 0), // ADD: no carry
+        (&[(0, 7), (1, 5)][..], 0x8015, 2, 1),      // SUB: no borrow
+        (&[(0, 5), (1, 7)][..], 0x8015, 254, 0),    // SUB: borrow
+        (&[(0, 5), (1, 7)][..], 0x8017, 2, 1),      // SUBN: no borrow
+        (&[(0, 7), (1, 5)][..], 0x8017, 254, 0),    // SUBN: borrow
+    ];
+    for (regs, opcode, expected, flag) in cases {
+        let cpu = run_alu(regs, opcode);
+        assert_eq!(
+            (cpu.reg[0], cpu.reg[0xF]),
+            (expected, flag),
+            "{:04x}",
+            opcode
+        );
+    }
+
+    // a stale flag is always overwritten
+    let cpu = run_alu(&[(0, 1), (1, 1), (0xF, 9)], 0x8014);
+    assert_eq!(cpu.reg[0xF], 0);
+
+    // when VF is the destination, the flag wins over the result
+    let cpu = run_alu(&[(0xF, 200), (1, 100)], 0x8F14);
+    assert_eq!(cpu.reg[0xF], 1);
+    let cpu = run_alu(&[(0xF, 7), (1, 5)], 0x8F15);
+    assert_eq!(cpu.reg[0xF], 1);
+}
+
+#[test]
+pub fn test_run_cycle_count() {
+    // test_addition's program: three additions, then the halt
+    let mut cpu = CPU::new();
+    cpu.write_system_mem(&[0x80, 0x14, 0x80, 0x24, 0x80, 0x34, 0x00, 0x00])
+        .unwrap();
+    assert_eq!(cpu.run(), Ok(4));
+    assert_eq!(cpu.run(), Ok(0)); // already halted
+
+    // each run between breakpoints counts only its own cycles
+    let mut cpu = CPU::new();
+    cpu.write_system_mem(&COUNT_TO_FIVE).unwrap();
+    cpu.add_breakpoint(0x004);
+    assert_eq!(cpu.run(), Ok(2)); // V0 = 0, V0 += 1
+    assert_eq!(cpu.run(), Ok(3)); // no skip (V0 != 5), jump back, V0 += 1
+}
+
+#[test]
+pub fn test_draw_callback() {
+    use std::{cell::RefCell, rc::Rc};
+
+    let frames = Rc::new(RefCell::new(Vec::new()));
+    let painted = Rc::clone(&frames);
+
+    let mut cpu = CPU::new();
+    cpu.set_draw_callback(Box::new(move |pixels| {
+        painted
+            .borrow_mut()
+            .push(pixels.iter().filter(|&&lit| lit).count())
+    }));
+    // draw the "0" glyph twice (lighting it, then erasing it), then clear the display
+    cpu.write_system_mem(&[
+        0x60, 0x00, // 0x000: V0 = 0
+        0xF0, 0x29, // 0x002: I = the "0" glyph
+        0xD0, 0x05, // 0x004: draw it
+        0x70, 0x00, // 0x006: (not a draw)
+        0xD0, 0x05, // 0x008: draw it again
+        0x00, 0xE0, // 0x00A: clear
+        0x00, 0x00,
+    ])
+    .unwrap();
+    cpu.run().unwrap();
+    assert_eq!(*frames.borrow(), [14, 0, 0]);
+}
+
+#[test]
+pub fn test_take_dirty() {
+    let mut cpu = CPU::new();
+    assert!(!cpu.take_dirty());
+
+    draw_sprite(&mut cpu, &[0x80], 0, 0);
+    assert!(cpu.take_dirty());
+    assert!(!cpu.take_dirty());
+
+    // opcodes that don't draw leave it clean
+    rerun(&mut cpu, &[0x70, 0x01, 0x00, 0x00]);
+    assert!(!cpu.take_dirty());
+
+    rerun(&mut cpu, &[0x00, 0xE0, 0x00, 0x00]);
+    assert!(cpu.take_dirty());
+}
+
+#[test]
+pub fn test_double_buffering() {
+    let mut cpu = CPU::new();
+    cpu.set_double_buffered(true);
+
+    // two sprites side by side, drawn into the back buffer
+    cpu.write_system_mem(&[
+        0x60, 0x00, // 0x000: V0 = 0
+        0x61, 0x00, // 0x002: V1 = 0
+        0xF0, 0x29, // 0x004: I = the "0" glyph
+        0xD0, 0x15, // 0x006: draw it at (0, 0)
+        0x60, 0x08, // 0x008: V0 = 8
+        0xD0, 0x15, // 0x00A: draw it at (8, 0)
+        0x00, 0x00,
+    ])
+    .unwrap();
+    cpu.run().unwrap();
+    assert!(cpu.framebuffer().iter().all(|&lit| !lit));
+    assert!(!cpu.take_dirty());
+
+    cpu.present();
+    assert_eq!(cpu.framebuffer().iter().filter(|&&lit| lit).count(), 28);
+    assert!(cpu.take_dirty());
+
+    // the front buffer keeps the last frame while the back buffer is cleared
+    rerun(&mut cpu, &[0x00, 0xE0, 0xD0, 0x15, 0x00, 0x00]);
+    cpu.present();
+    let lit = cpu.framebuffer_hash();
+    rerun(&mut cpu, &[0x00, 0xE0, 0x00, 0x00]);
+    assert_eq!(cpu.framebuffer_hash(), lit);
+    cpu.present();
+    assert_ne!(cpu.framebuffer_hash(), lit);
+
+    // single-buffering shows each draw straight away
+    cpu.set_double_buffered(false);
+    rerun(&mut cpu, &[0xD0, 0x15, 0x00, 0x00]);
+    assert_eq!(cpu.framebuffer_hash(), lit);
+}
+
+#[test]
+pub fn test_render_ascii_with() {
+    let mut cpu = CPU::new();
+    draw_sprite(&mut cpu, &[0b1100_0000, 0b0110_0000], 0, 0);
+
+    let text = cpu.render_ascii_with('*', '.');
+    let rows: Vec<&str> = text.lines().collect();
+    assert_eq!(rows[0], format!("**{}", ".".repeat(62)));
+    assert_eq!(rows[1], format!(".**{}", ".".repeat(61)));
+    assert_eq!(rows[2], ".".repeat(64));
+    assert!(!text.contains(['#', ' ']));
+
+    assert_eq!(cpu.render_ascii(), cpu.render_ascii_with('#', ' '));
+}
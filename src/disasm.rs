@@ -0,0 +1,119 @@
+use crate::opcode::Opcode;
+
+/// Translate a CHIP-8 opcode into its mnemonic, e.g. 0x8014 -> "ADD V0, V1".
+///
+/// Words that aren't valid opcodes are rendered as raw data (e.g. "DW 0xFFFF").
+pub fn disassemble(opcode: u16) -> String {
+    match Opcode::try_from(opcode) {
+        Ok(instruction) => instruction.to_string(),
+        Err(_) => format!("DW 0x{:04X}", opcode),
+    }
+}
+
+/// Find which addresses in a program hold reachable instructions, following every path
+/// control can take from its first instruction (e.g. both outcomes of a skip, or a CALL and
+/// its return). `program` is loaded at `start`; the result has one flag per byte, set where
+/// an instruction starts.
+///
+/// The walk stops at HALT, RET, invalid opcodes, and anything `LD I` points at (which is
+/// sprite or other data), so the bytes it never reaches can be shown as data. Targets outside
+/// the program (e.g. the font, in system memory) are never walked into.
+pub fn find_code(program: &[u8], start: usize) -> Vec<bool> {
+    let opcode_at = |idx: usize| u16::from_be_bytes([program[idx], program[idx + 1]]);
+
+    // everything control can reach, treating nothing as data yet...
+    let reachable = walk(program, start, &vec![false; program.len()]);
+    // ...tells us what LD I points at, and so where the walk has to stop
+    let mut data = vec![false; program.len()];
+    for idx in (0..program.len()).filter(|&idx| reachable[idx]) {
+        if let Ok(Opcode::LoadIndex { nnn }) = Opcode::try_from(opcode_at(idx))
+            && let Some(target) = (nnn as usize).checked_sub(start)
+            && target < program.len()
+        {
+            data[target] = true;
+        }
+    }
+    walk(program, start, &data)
+}
+
+/// flag the start of each instruction reachable from `start`, without entering `data`
+fn walk(program: &[u8], start: usize, data: &[bool]) -> Vec<bool> {
+    let mut code = vec![false; program.len()];
+    let mut pending = vec![start];
+
+    while let Some(addr) = pending.pop() {
+        let Some(idx) = addr.checked_sub(start) else {
+            continue;
+        };
+        if idx + 1 >= program.len() || code[idx] || data[idx] {
+            continue;
+        }
+        let opcode = u16::from_be_bytes([program[idx], program[idx + 1]]);
+        let Ok(opcode) = Opcode::try_from(opcode) else {
+            continue;
+        };
+        code[idx] = true;
+        pending.extend(successors(addr, opcode));
+    }
+    code
+}
+
+/// the addresses control can move to after executing `opcode` at `addr`
+fn successors(addr: usize, opcode: Opcode) -> Vec<usize> {
+    use Opcode::*;
+
+    match opcode {
+        Halt | Return => vec![],
+        // (a computed jump usually lands on a table starting at its base)
+        Jump { nnn } | JumpOffset { nnn } => vec![nnn as usize],
+        Call { nnn } => vec![nnn as usize, addr + 2],
+        SkipEqImmediate { .. }
+        | SkipNeImmediate { .. }
+        | SkipEqRegister { .. }
+        | SkipNeRegister { .. }
+        | SkipKey { .. }
+        | SkipNotKey { .. } => vec![addr + 2, addr + 4],
+        _ => vec![addr + 2],
+    }
+}
+
+#[test]
+pub fn test_disassemble() {
+    assert_eq!(disassemble(0x0000), "HALT");
+    assert_eq!(disassemble(0x00E0), "CLS");
+    assert_eq!(disassemble(0x00EE), "RET");
+    assert_eq!(disassemble(0x1200), "JP 0x200");
+    assert_eq!(disassemble(0x2100), "CALL 0x100");
+    assert_eq!(disassemble(0x73EE), "ADD V3, 0xEE");
+    assert_eq!(disassemble(0x8014), "ADD V0, V1");
+    assert_eq!(disassemble(0x8231), "OR V2, V3");
+    assert_eq!(disassemble(0xA2F0), "LD I, 0x2F0");
+    assert_eq!(disassemble(0xD015), "DRW V0, V1, 5");
+    assert_eq!(disassemble(0xFA33), "LD B, VA");
+
+    // invalid opcodes are shown as raw data
+    assert_eq!(disassemble(0x5121), "DW 0x5121");
+    assert_eq!(disassemble(0xFFFF), "DW 0xFFFF");
+}
+
+#[test]
+pub fn test_find_code() {
+    let program = [
+        0x30, 0x00, // 0x100: SE V0, 0x00
+        0x21, 0x0A, // 0x102: CALL 0x10A
+        0xA1, 0x0E, // 0x104: LD I, 0x10E
+        0xD0, 0x12, // 0x106: DRW V0, V1, 2
+        0x00, 0x00, // 0x108: HALT
+        0x71, 0x01, // 0x10A: ADD V1, 0x01
+        0x00, 0xEE, // 0x10C: RET
+        0x00, 0xEE, // 0x10E: a sprite, which happens to read as RET
+        0x12, 0x00, // 0x110: unreachable, though it reads as JP 0x200
+    ];
+    let code = find_code(&program, 0x100);
+    let starts: Vec<usize> = (0..program.len()).filter(|&idx| code[idx]).collect();
+    assert_eq!(starts, [0x0, 0x2, 0x4, 0x6, 0x8, 0xA, 0xC]);
+
+    // a path falling through into the sprite stops there
+    let code = find_code(&[0xA1, 0x02, 0x00, 0xE0], 0x100);
+    assert_eq!(code, [true, false, false, false]);
+}
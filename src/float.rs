@@ -1,4 +1,8 @@
 use colored::Colorize;
+use std::num::FpCategory;
+
+// (re-exported, as floats were its first users)
+pub use crate::bytes::Endianness;
 
 ///  bit-pattern of the three components encoded into the f32 type:
 ///
@@ -7,11 +11,12 @@ use colored::Colorize;
 /// ^
 /// (sign)
 ///
-/// (assumes val is BigEndian)
+/// (bits are numbered from the MSB, irrespective of the host's byte order;
+/// use `from_bytes` to deconstruct a raw byte buffer of known `Endianness`)
 ///
-pub struct DeconstructedFloat32<'a> {
-    // reference to the original float this deconstruction is based on.
-    float: &'a f32,
+pub struct DeconstructedFloat32 {
+    // the original float this deconstruction is based on.
+    float: f32,
 
     // IEEE 754-XXXX standards define:
     //
@@ -23,9 +28,33 @@ pub struct DeconstructedFloat32<'a> {
     mantissa_bytes: [u8; 3],
 }
 
-impl<'a> DeconstructedFloat32<'a> {
+/// significant digits needed for any f32 to survive a round-trip through its decimal form
+pub const DEFAULT_PRECISION: usize = 9;
+
+/// How to lay out the table rendered by `DeconstructedFloat32::to_report_with`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ReportOptions {
+    /// significant digits shown in the value
+    pub precision: usize,
+    /// width of the column of labels (at least 12 keeps the labels aligned)
+    pub label_width: usize,
+    /// width of the column of bits (32 bits wide, or padded out to this)
+    pub bits_width: usize,
+}
+
+impl Default for ReportOptions {
+    fn default() -> ReportOptions {
+        ReportOptions {
+            precision: DEFAULT_PRECISION,
+            label_width: 12,
+            bits_width: 32,
+        }
+    }
+}
+
+impl DeconstructedFloat32 {
     /// create a deconstructed float from an input f32
-    pub fn new(val: &'a f32) -> DeconstructedFloat32<'a> {
+    pub fn new(val: f32) -> DeconstructedFloat32 {
         // convert the input to u32 for bit-manipuation
         let bits: u32 = val.to_bits();
 
@@ -53,19 +82,1303 @@ impl<'a> DeconstructedFloat32<'a> {
         }
     }
 
+    /// create a deconstructed float from explicit component values, e.g. (0, 127, [0, 0, 0]) is 1.0.
+    ///
+    /// the sign must be 0 or 1, and the mantissa is 23 bits so its top byte holds only 7.
+    pub fn from_parts(
+        sign: u8,
+        exponent: u8,
+        mantissa: [u8; 3],
+    ) -> Result<DeconstructedFloat32, String> {
+        if sign > 1 {
+            return Err(format!("Sign bit {} must be 0 or 1", sign));
+        }
+        if mantissa[0] > 0x7F {
+            return Err(format!(
+                "Mantissa {:02X}{:02X}{:02X} must fit in 23 bits (its top byte at most 7F)",
+                mantissa[0], mantissa[1], mantissa[2]
+            ));
+        }
+
+        let parts = DeconstructedFloat32 {
+            float: 0.0,
+            sign_bit: sign,
+            exponent_byte: exponent,
+            mantissa_bytes: mantissa,
+        };
+        Ok(DeconstructedFloat32 {
+            float: parts.to_f32(),
+            ..parts
+        })
+    }
+
+    /// create a deconstructed float from four raw bytes stored in the given byte order
+    pub fn from_bytes(bytes: &[u8; 4], endianness: Endianness) -> DeconstructedFloat32 {
+        let val = match endianness {
+            Endianness::Big => f32::from_be_bytes(*bytes),
+            Endianness::Little => f32::from_le_bytes(*bytes),
+        };
+        DeconstructedFloat32::new(val)
+    }
+
+    /// the four raw bytes of the float, in the given byte order (the inverse of `from_bytes`)
+    pub fn to_bytes(&self, endianness: Endianness) -> [u8; 4] {
+        match endianness {
+            Endianness::Big => self.float.to_be_bytes(),
+            Endianness::Little => self.float.to_le_bytes(),
+        }
+    }
+
+    /// the raw bit pattern of the original float
+    pub fn bits(&self) -> u32 {
+        self.float.to_bits()
+    }
+
+    /// reconstruct the value encoded by the sign, exponent and mantissa components
+    pub fn value(&self) -> f32 {
+        let sign = if self.sign_bit == 1 { -1.0 } else { 1.0 };
+        let mantissa = self.mantissa() as f64 / (1 << 23) as f64;
+
+        let magnitude = match self.exponent_byte {
+            // no implicit leading bit, and the exponent is pinned at its minimum
+            0 => mantissa * 2f64.powi(-126),
+            // all exponent bits set encodes the special values
+            0xFF if mantissa == 0.0 => f64::INFINITY,
+            0xFF => f64::NAN,
+            // normals have an implicit leading bit (1.mantissa)
+            e => (1.0 + mantissa) * 2f64.powi(e as i32 - 127),
+        };
+        (sign * magnitude) as f32
+    }
+
+    /// reassemble the components back into an f32, bit-for-bit
+    pub fn to_f32(&self) -> f32 {
+        let bits =
+            ((self.sign_bit as u32) << 31) | ((self.exponent_byte as u32) << 23) | self.mantissa();
+        f32::from_bits(bits)
+    }
+
+    /// the 23-bit mantissa field as a number, e.g. 1.5 -> 0x400000
+    pub fn mantissa_int(&self) -> u32 {
+        self.mantissa()
+    }
+
+    /// the mantissa with the implicit leading bit set for normals, e.g. 1.5 -> 0xC00000,
+    /// so that a normal's magnitude is significand * 2^(exponent - 150)
+    pub fn significand_int(&self) -> u32 {
+        match self.classify() {
+            FpCategory::Normal => self.mantissa() | 1 << 23,
+            _ => self.mantissa(),
+        }
+    }
+
+    /// the leading bit of the significand, which isn't stored: 1 for normals, and 0 for
+    /// subnormals and zero (inf and NaN have no significand, so None)
+    pub fn implicit_bit(&self) -> Option<u8> {
+        match self.classify() {
+            FpCategory::Normal => Some(1),
+            FpCategory::Subnormal | FpCategory::Zero => Some(0),
+            FpCategory::Infinite | FpCategory::Nan => None,
+        }
+    }
+
+    /// the IEEE 754 class of the deconstructed float (e.g. normal, subnormal, zero)
+    pub fn classify(&self) -> FpCategory {
+        match (self.exponent_byte, self.mantissa()) {
+            (0, 0) => FpCategory::Zero,
+            (0, _) => FpCategory::Subnormal,
+            (0xFF, 0) => FpCategory::Infinite,
+            (0xFF, _) => FpCategory::Nan,
+            _ => FpCategory::Normal,
+        }
+    }
+
+    /// lowercase name of the class, as used in reports
+    pub fn class_name(&self) -> &'static str {
+        match self.classify() {
+            FpCategory::Zero => "zero",
+            FpCategory::Subnormal => "subnormal",
+            FpCategory::Infinite => "infinite",
+            FpCategory::Nan => "nan",
+            FpCategory::Normal => "normal",
+        }
+    }
+
+    /// whether the magnitude is exactly a power of two (the sign is ignored, so -8.0 is 2^3)
+    pub fn is_power_of_two(&self) -> bool {
+        self.power_of_two_exponent().is_some()
+    }
+
+    /// the exponent `e` for which the magnitude is exactly 2^e, if there is one
+    fn power_of_two_exponent(&self) -> Option<i32> {
+        let mantissa = self.mantissa();
+        match self.classify() {
+            // the implicit leading bit is the only set bit
+            FpCategory::Normal if mantissa == 0 => Some(self.exponent_byte as i32 - 127),
+            // no implicit bit, so exactly one mantissa bit must be set
+            FpCategory::Subnormal if mantissa.count_ones() == 1 => {
+                Some(mantissa.trailing_zeros() as i32 - 149)
+            }
+            _ => None,
+        }
+    }
+
+    /// whether the value is a whole number, i.e. none of its set bits lie after the binary point
+    /// (zero is an integer; inf and NaN are not)
+    pub fn is_integer(&self) -> bool {
+        match self.integer_and_exponent() {
+            Some((significand, exp)) => {
+                exp >= 0 || significand.trailing_zeros() >= exp.unsigned_abs()
+            }
+            None => false,
+        }
+    }
+
+    /// number of significant bits used by the value (i.e. how "round" it is in binary).
+    ///
+    /// counts from the leading 1 (the implicit bit for normals) down to the last set mantissa bit,
+    /// so 1.0 uses 1 bit, 1.5 (0b1.1) uses 2, and zero or non-finite values use none.
+    pub fn significant_bits(&self) -> u32 {
+        let mantissa = self.mantissa();
+        // trailing_zeros of an empty field is 32, so clamp to the 23 bits actually stored
+        let trailing_zeros = mantissa.trailing_zeros().min(23);
+        match self.classify() {
+            FpCategory::Normal => 23 - trailing_zeros + 1,
+            // without an implicit bit, leading zeros aren't significant either
+            FpCategory::Subnormal => 32 - mantissa.leading_zeros() - trailing_zeros,
+            _ => 0,
+        }
+    }
+
+    /// convert to a signed Qm.n fixed-point number, with `int_bits` (including the sign bit)
+    /// and `frac_bits` making up the total width, e.g. 1.5 in Q8.8 is 0x0180.
+    ///
+    /// values that don't fit (including inf and NaN) saturate to the nearest limit, and the
+    /// total width must be 1 to 63 bits.
+    pub fn to_fixed(&self, int_bits: u32, frac_bits: u32) -> Result<i64, FixedError> {
+        let width = int_bits
+            .checked_add(frac_bits)
+            .filter(|width| (1..=63).contains(width))
+            .ok_or(FixedError::InvalidWidth {
+                int_bits,
+                frac_bits,
+            })?;
+
+        let max = (1_i64 << (width - 1)) - 1;
+        let min = -(1_i64 << (width - 1));
+
+        let scaled = (self.value() as f64 * 2f64.powi(frac_bits as i32)).round();
+        match scaled {
+            v if v.is_nan() => Err(FixedError::Saturated(0)),
+            v if v > max as f64 => Err(FixedError::Saturated(max)),
+            v if v < min as f64 => Err(FixedError::Saturated(min)),
+            v => Ok(v as i64),
+        }
+    }
+
+    /// the reconstructed value in decimal, rounded to `precision` significant digits
+    /// (digits before the decimal point are never dropped, so 123456 stays as it is)
+    pub fn decimal(&self, precision: usize) -> String {
+        let value = self.value();
+        if !value.is_finite() || value == 0.0 {
+            return format!("{:?}", value);
+        }
+        // take the exponent after rounding, so 9.99 to 2 digits is treated as 1e1
+        let exp: i32 = self
+            .scientific(precision)
+            .split_once('e')
+            .and_then(|(_, exp)| exp.parse().ok())
+            .unwrap_or(0);
+        let decimals = (precision.max(1) as i32 - 1 - exp).max(0) as usize;
+        format!("{:.*}", decimals, value)
+    }
+
+    /// the reconstructed value in scientific notation, rounded to `precision` significant digits
+    pub fn scientific(&self, precision: usize) -> String {
+        format!("{:.*e}", precision.max(1) - 1, self.value())
+    }
+
+    /// the exact value as a fraction in lowest terms, e.g. 0.75 -> (3, 4).
+    ///
+    /// every finite float is an integer over a power of two; this is None for inf and NaN,
+    /// and for the very large or very small values whose fraction doesn't fit in an i64.
+    pub fn exact_rational(&self) -> Option<(i64, i64)> {
+        let (significand, exp) = self.integer_and_exponent()?;
+        if significand == 0 {
+            return Some((0, 1));
+        }
+
+        // cancel the common factors of two
+        let shift = significand.trailing_zeros();
+        let numerator = (significand >> shift) as i64;
+        let exp = exp + shift as i32;
+
+        let (numerator, denominator) = match exp {
+            0..=62 => (numerator.checked_mul(1 << exp)?, 1),
+            -62..0 => (numerator, 1 << -exp),
+            _ => return None,
+        };
+        let sign = if self.sign_bit == 1 { -1 } else { 1 };
+        Some((sign * numerator, denominator))
+    }
+
+    /// the exact value written out in full, e.g. 0.1 -> "0.100000001490116119384765625".
+    ///
+    /// unlike `decimal`, nothing is rounded: every finite float has a finite decimal expansion,
+    /// though the smallest subnormals run to well over a hundred digits.
+    pub fn exact_decimal(&self) -> String {
+        let Some((significand, exp)) = self.integer_and_exponent() else {
+            return format!("{:?}", self.float);
+        };
+
+        // little-endian decimal digits of the significand
+        let mut digits = Vec::new();
+        let mut rest = significand;
+        while rest > 0 {
+            digits.push((rest % 10) as u8);
+            rest /= 10;
+        }
+
+        // for e < 0, m * 2^e is (m * 5^-e) / 10^-e: scale by 5s, then place the decimal point
+        let (factor, places) = if exp >= 0 { (2, 0) } else { (5, -exp as usize) };
+        for _ in 0..exp.unsigned_abs() {
+            multiply_digits(&mut digits, factor);
+        }
+        digits.resize(digits.len().max(places + 1), 0);
+
+        let digit = |d: &u8| char::from(b'0' + d);
+        let int: String = digits[places..].iter().rev().map(digit).collect();
+        let frac: String = digits[..places].iter().rev().map(digit).collect();
+        let frac = frac.trim_end_matches('0');
+
+        let sign = if self.sign_bit == 1 { "-" } else { "" };
+        match frac {
+            "" => format!("{}{}", sign, int),
+            _ => format!("{}{}.{}", sign, int, frac),
+        }
+    }
+
+    /// how far the stored value is from the number it was meant to represent (e.g. the
+    /// decimal a user typed, parsed at higher precision), as (absolute, relative) errors.
+    ///
+    /// both are zero when the number is exactly representable.
+    pub fn representation_error(&self, intended: f64) -> (f64, f64) {
+        let absolute = self.value() as f64 - intended;
+        let relative = match intended {
+            0.0 => 0.0,
+            _ => absolute / intended.abs(),
+        };
+        (absolute, relative)
+    }
+
     /// display the contents of the deconstructed float.
     pub fn print(&self) {
+        print!("{}", self.to_report());
+    }
+
+    /// render the contents of the deconstructed float as a table.
+    pub fn to_report(&self) -> String {
+        self.to_report_with(&ReportOptions::default())
+    }
+
+    /// render the contents of the deconstructed float as a table, laid out as per `options`.
+    pub fn to_report_with(&self, options: &ReportOptions) -> String {
+        let precision = options.precision;
+
+        let mut report = String::new();
+        report += &format!("\nInput: {:?}\n\n", self.float);
+        report += &self.to_bit_rows_with(options);
+        report += &format!(
+            "\nValue: {} ({})\n",
+            self.decimal(precision),
+            self.scientific(precision)
+        );
+        report += &format!(
+            "Mantissa: {} (significand {})\n",
+            self.mantissa_int(),
+            self.significand_int()
+        );
+        if let Some(bit) = self.implicit_bit() {
+            let m_ = self.mantissa_bytes;
+            report += &format!(
+                "Implicit bit: {} ({}, so the significand is {}.{:07b}{:08b}{:08b})\n",
+                bit,
+                self.class_name(),
+                bit,
+                m_[0],
+                m_[1],
+                m_[2]
+            );
+        }
+        if let Some((numerator, denominator)) = self.exact_rational() {
+            report += &format!("Exact: {}/{}\n", numerator, denominator);
+        }
+        if let Some(exp) = self.power_of_two_exponent() {
+            let sign = if self.sign_bit == 1 { "-" } else { "" };
+            report += &format!("\nNote: = {}2^{} exactly\n", sign, exp);
+        }
+        report += "\n";
+        report
+    }
+
+    /// render just the rows of the table: the input bits, then each component highlighted.
+    pub fn to_bit_rows(&self) -> String {
+        self.to_bit_rows_with(&ReportOptions::default())
+    }
+
+    /// render just the rows of the table, with the column widths given in `options`.
+    pub fn to_bit_rows_with(&self, options: &ReportOptions) -> String {
         let sign_bit_txt = format!("{:b}", self.sign_bit).on_red();
         let exponent_txt = format!("{:08b}", self.exponent_byte).on_red();
 
         let m_ = self.mantissa_bytes;
         let mantissa_txt = format!("{:07b}{:08b}{:08b}", m_[0], m_[1], m_[2]).on_red();
 
-        println!("\nInput: {:?}\n", self.float);
-        println!("| input (bits) | {:032b} |", self.float.to_bits());
-        println!("| sign         | {}{:031b} |", sign_bit_txt, 0);
-        println!("| exponent     | {:01b}{}{:023b} |", 0, exponent_txt, 0);
-        println!("| mantissa     | {:09b}{} |", 0, mantissa_txt);
+        // pad by hand, since the highlighted bits carry (invisible) color codes
+        let width = options.label_width;
+        let pad = " ".repeat(options.bits_width.saturating_sub(32));
+
+        let mut rows = String::new();
+        rows += &format!(
+            "| {:<width$} | {:032b}{} |\n",
+            "input (bits)",
+            self.bits(),
+            pad
+        );
+        rows += &format!(
+            "| {:<width$} | {}{:031b}{} |\n",
+            "sign", sign_bit_txt, 0, pad
+        );
+        rows += &format!(
+            "| {:<width$} | {:01b}{}{:023b}{} |\n",
+            "exponent", 0, exponent_txt, 0, pad
+        );
+        rows += &format!(
+            "| {:<width$} | {:09b}{}{} |\n",
+            "mantissa", 0, mantissa_txt, pad
+        );
+        rows
+    }
+
+    /// render the contents of the deconstructed float as a GitHub-flavored markdown table.
+    pub fn to_markdown(&self) -> String {
+        let m_ = self.mantissa_bytes;
+
+        let mut report = String::new();
+        report += &format!("**Input:** `{:?}`\n\n", self.float);
+        report += "| component | bits |\n";
+        report += "| --- | --- |\n";
+        report += &format!("| input (bits) | `{:032b}` |\n", self.bits());
+        report += &format!("| sign | `{:b}` |\n", self.sign_bit);
+        report += &format!("| exponent | `{:08b}` |\n", self.exponent_byte);
+        report += &format!("| mantissa | `{:07b}{:08b}{:08b}` |\n", m_[0], m_[1], m_[2]);
+        report
+    }
+
+    /// render the deconstructed float as a row of comma-separated values (see `CSV_HEADER`).
+    pub fn to_csv_row(&self) -> String {
+        format!(
+            "{:?},{},{},{},{:?},{}\n",
+            self.float,
+            self.sign_bit,
+            self.exponent_byte,
+            self.mantissa(),
+            self.value(),
+            self.class_name(),
+        )
+    }
+
+    /// render the deconstructed float as `key=value` lines (e.g. `exponent=127`), for shell scripts.
+    pub fn to_kv(&self) -> String {
+        format!(
+            "input={:?}\nsign={}\nexponent={}\nmantissa={}\nvalue={:?}\nclass={}\n",
+            self.float,
+            self.sign_bit,
+            self.exponent_byte,
+            self.mantissa(),
+            self.value(),
+            self.class_name(),
+        )
+    }
+
+    /// render the deconstructed float as a JSON object (inf and NaN, which JSON can't express,
+    /// are null, though the bits still say exactly what they were).
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{ \"input\": {}, \"bits\": {}, \"sign\": {}, \"exponent\": {}, \"mantissa\": {}, \"value\": {}, \"class\": \"{}\" }}",
+            json_number(format!("{:?}", self.float), self.float.is_finite()),
+            self.bits(),
+            self.sign_bit,
+            self.exponent_byte,
+            self.mantissa(),
+            json_number(format!("{:?}", self.value()), self.value().is_finite()),
+            self.class_name(),
+        )
+    }
+
+    /// flag which bits of each component differ between two deconstructed floats
+    pub fn diff(&self, other: &DeconstructedFloat32) -> FloatDiff {
+        FloatDiff {
+            sign: self.sign_bit ^ other.sign_bit,
+            exponent: self.exponent_byte ^ other.exponent_byte,
+            mantissa: self.mantissa() ^ other.mantissa(),
+        }
+    }
+
+    /// display two deconstructed floats side-by-side, highlighting the bits that differ.
+    pub fn print_comparison(&self, other: &DeconstructedFloat32) {
+        let diff = self.diff(other);
+
+        let sign_a = highlight_bits(self.sign_bit as u32, diff.sign as u32, 1);
+        let sign_b = highlight_bits(other.sign_bit as u32, diff.sign as u32, 1);
+        let expo_a = highlight_bits(self.exponent_byte as u32, diff.exponent as u32, 8);
+        let expo_b = highlight_bits(other.exponent_byte as u32, diff.exponent as u32, 8);
+        let mant_a = highlight_bits(self.mantissa(), diff.mantissa, 23);
+        let mant_b = highlight_bits(other.mantissa(), diff.mantissa, 23);
+
+        println!("\nCompare: {:?} vs {:?}\n", self.float, other.float);
+        println!("| field    | {:<23} | {:<23} |", "a", "b");
+        println!("| sign     | {}{:22} | {}{:22} |", sign_a, "", sign_b, "");
+        println!("| exponent | {}{:15} | {}{:15} |", expo_a, "", expo_b, "");
+        println!("| mantissa | {} | {} |", mant_a, mant_b);
+        println!(
+            "\nDiffering bits: sign={}, exponent={}, mantissa={}",
+            diff.sign.count_ones(),
+            diff.exponent.count_ones(),
+            diff.mantissa.count_ones(),
+        );
         println!();
     }
+
+    /// the magnitude as an integer significand `m` and exponent `e`, such that it is m * 2^e
+    /// exactly (None for inf and NaN)
+    fn integer_and_exponent(&self) -> Option<(u32, i32)> {
+        match self.classify() {
+            FpCategory::Zero => Some((0, 0)),
+            FpCategory::Infinite | FpCategory::Nan => None,
+            FpCategory::Subnormal => Some((self.mantissa(), -149)),
+            FpCategory::Normal => Some((self.significand_int(), self.exponent_byte as i32 - 150)),
+        }
+    }
+
+    /// reassemble the mantissa bytes into the 23-bit field
+    fn mantissa(&self) -> u32 {
+        let m_ = self.mantissa_bytes;
+        ((m_[0] as u32) << 16) | ((m_[1] as u32) << 8) | (m_[2] as u32)
+    }
+}
+
+/// a formatted float as a JSON value: itself if finite, otherwise null
+fn json_number(formatted: String, finite: bool) -> String {
+    match finite {
+        true => formatted,
+        false => "null".to_string(),
+    }
+}
+
+/// multiply a little-endian list of decimal digits by a small factor, in place
+fn multiply_digits(digits: &mut Vec<u8>, factor: u32) {
+    let mut carry = 0;
+    for digit in digits.iter_mut() {
+        let product = *digit as u32 * factor + carry;
+        *digit = (product % 10) as u8;
+        carry = product / 10;
+    }
+    while carry > 0 {
+        digits.push((carry % 10) as u8);
+        carry /= 10;
+    }
+}
+
+///  bit-pattern of the three components encoded into the f64 type:
+///
+/// [] [<-- EXPONENT_MASK -->] [<---------- MANTISSA_MASK ---------->]
+/// 63 62 61 .. 53 52          51 50 49 .. 0
+/// ^
+/// (sign)
+///
+pub struct DeconstructedFloat64 {
+    // the original float this deconstruction is based on.
+    float: f64,
+
+    // RADIX  = 2 (base)
+    // BIAS = 1023 (exponent offset)
+    //
+    sign_bit: u8,
+    exponent: u16,
+    mantissa: u64,
+}
+
+impl DeconstructedFloat64 {
+    /// create a deconstructed float from an input f64
+    pub fn new(val: f64) -> DeconstructedFloat64 {
+        let bits: u64 = val.to_bits();
+
+        const SIGN_MASK: u64 = 1 << 63; // sign-bit
+        const EXPO_MASK: u64 = 0x7FF << 52; // 11 exponent bits
+        const MANT_MASK: u64 = (1 << 52) - 1; // 52 mantissa bits
+
+        DeconstructedFloat64 {
+            float: val,
+            sign_bit: ((bits & SIGN_MASK) >> 63) as u8,
+            exponent: ((bits & EXPO_MASK) >> 52) as u16,
+            mantissa: bits & MANT_MASK,
+        }
+    }
+
+    /// reassemble the components back into an f64, bit-for-bit
+    pub fn to_f64(&self) -> f64 {
+        f64::from_bits(
+            ((self.sign_bit as u64) << 63) | ((self.exponent as u64) << 52) | self.mantissa,
+        )
+    }
+
+    /// display the contents of the deconstructed float.
+    pub fn print(&self) {
+        print!("{}", self.to_report());
+    }
+
+    /// render the deconstructed float as a JSON object (inf and NaN are null, as for f32).
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{ \"input\": {}, \"bits\": {}, \"sign\": {}, \"exponent\": {}, \"mantissa\": {} }}",
+            json_number(format!("{:?}", self.float), self.float.is_finite()),
+            self.float.to_bits(),
+            self.sign_bit,
+            self.exponent,
+            self.mantissa,
+        )
+    }
+
+    /// render the contents of the deconstructed float as a table.
+    pub fn to_report(&self) -> String {
+        let sign_bit_txt = format!("{:b}", self.sign_bit).on_red();
+        let exponent_txt = format!("{:011b}", self.exponent).on_red();
+        let mantissa_txt = format!("{:052b}", self.mantissa).on_red();
+
+        let mut report = String::new();
+        report += &format!("\nInput: {:?}\n\n", self.float);
+        report += &format!("| input (bits) | {:064b} |\n", self.float.to_bits());
+        report += &format!("| sign         | {}{:063b} |\n", sign_bit_txt, 0);
+        report += &format!("| exponent     | {:01b}{}{:052b} |\n", 0, exponent_txt, 0);
+        report += &format!("| mantissa     | {:012b}{} |\n", 0, mantissa_txt);
+        report += "\n";
+        report
+    }
+}
+
+/// reasons a fixed-point conversion failed
+#[derive(Debug, PartialEq)]
+pub enum FixedError {
+    /// the value didn't fit, so was clamped to the given limit
+    Saturated(i64),
+    /// the total width of the format isn't 1 to 63 bits
+    InvalidWidth { int_bits: u32, frac_bits: u32 },
+}
+
+/// columns of the rows produced by `DeconstructedFloat32::to_csv_row`
+pub const CSV_HEADER: &str = "input,sign,exponent,mantissa,value,class\n";
+
+/// bit-masks flagging the bits that differ between two deconstructed floats,
+/// (a set bit indicates a difference at that position within the component).
+#[derive(Debug, PartialEq)]
+pub struct FloatDiff {
+    pub sign: u8,
+    pub exponent: u8,
+    pub mantissa: u32,
+}
+
+/// render the lowest `width` bits of `bits` as binary, marking those set in `diff`.
+fn highlight_bits(bits: u32, diff: u32, width: usize) -> String {
+    (0..width)
+        .rev()
+        .map(|idx| {
+            let bit = format!("{}", (bits >> idx) & 1);
+            if (diff >> idx) & 1 == 1 {
+                bit.on_red().to_string()
+            } else {
+                bit
+            }
+        })
+        .collect()
+}
+
+/// parse a float from either a decimal literal (e.g. "1.5") or a C99 hex-float (e.g. "0x1.8p1").
+pub fn parse_f32(input: &str) -> Result<f32, String> {
+    match split_hex_float(input) {
+        Some((sign, hex)) => parse_hex_float(hex).map(|v| (sign * v) as f32),
+        // decimals are parsed directly, so they're only rounded once
+        None => input
+            .parse::<f32>()
+            .map_err(|_| format!("'{input}' is not a valid decimal or hex float")),
+    }
+}
+
+/// parse a double from either a decimal literal (e.g. "0.1") or a C99 hex-float (e.g. "0x1.8p1").
+pub fn parse_f64(input: &str) -> Result<f64, String> {
+    match split_hex_float(input) {
+        Some((sign, hex)) => parse_hex_float(hex).map(|v| sign * v),
+        None => input
+            .parse::<f64>()
+            .map_err(|_| format!("'{input}' is not a valid decimal or hex float")),
+    }
+}
+
+/// split a hex-float into its sign (1.0 or -1.0) and the body after its "0x" prefix,
+/// or `None` if it isn't one
+fn split_hex_float(input: &str) -> Option<(f64, &str)> {
+    let (sign, unsigned) = match input.strip_prefix('-') {
+        Some(rest) => (-1.0, rest),
+        None => (1.0, input.strip_prefix('+').unwrap_or(input)),
+    };
+    let hex = unsigned
+        .strip_prefix("0x")
+        .or_else(|| unsigned.strip_prefix("0X"))?;
+    Some((sign, hex))
+}
+
+/// parse the body of a hex-float (i.e. without its sign or "0x" prefix): "<hex>[.<hex>][p<exp>]"
+fn parse_hex_float(hex: &str) -> Result<f64, String> {
+    let err = || format!("'0x{hex}' is not a valid hex float (expected e.g. 0x1.8p3)");
+
+    // the binary exponent is optional and defaults to 2^0
+    let (digits, exp) = match hex.split_once(['p', 'P']) {
+        Some((digits, exp)) => (digits, exp.parse::<i32>().map_err(|_| err())?),
+        None => (hex, 0),
+    };
+    let (int_digits, frac_digits) = digits.split_once('.').unwrap_or((digits, ""));
+    let all_digits = format!("{int_digits}{frac_digits}");
+    // (checked up front, as from_str_radix would also accept a sign)
+    if all_digits.is_empty() || !all_digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(err());
+    }
+
+    // each fractional hex digit shifts the binary point four places, as does each trailing
+    // zero dropped from the digits (leading zeros are insignificant, so are dropped for free)
+    let significant = all_digits.trim_start_matches('0');
+    let trimmed = significant.trim_end_matches('0');
+    let shift = 4 * (significant.len() as i64 - trimmed.len() as i64 - frac_digits.len() as i64);
+
+    // a u64 holds 16 hex digits: any more would have to be rounded off, so are rejected
+    if trimmed.len() > 16 {
+        return Err(err());
+    }
+    let mantissa = match trimmed {
+        "" => return Ok(0.0),
+        trimmed => u64::from_str_radix(trimmed, 16).map_err(|_| err())?,
+    };
+
+    // (a mantissa below 2^64 is zero or infinite well within +/-2200, so clamping can't
+    // change the result, but keeps the arithmetic in range)
+    let scale = (exp as i64 + shift).clamp(-2200, 2200) as i32;
+    Ok(scale_by_pow2(mantissa as f64, scale))
+}
+
+/// `value * 2^exp`, in steps small enough that no intermediate power of two under- or
+/// overflows, so (for `value` of at least 1) the result only rounds once, at the last step
+fn scale_by_pow2(mut value: f64, mut exp: i32) -> f64 {
+    const STEP: i32 = 1000;
+    while exp > STEP {
+        value *= 2f64.powi(STEP);
+        exp -= STEP;
+    }
+    while exp < -STEP {
+        value *= 2f64.powi(-STEP);
+        exp += STEP;
+    }
+    value * 2f64.powi(exp)
+}
+
+/// parse a raw u32 bit-pattern written in hex, with or without a "0x" prefix (e.g. "0x3f800000").
+pub fn parse_bits(input: &str) -> Result<u32, String> {
+    let hex = input
+        .strip_prefix("0x")
+        .or_else(|| input.strip_prefix("0X"))
+        .unwrap_or(input);
+
+    u32::from_str_radix(hex, 16)
+        .map_err(|_| format!("'{input}' is not a valid 32-bit hex pattern (e.g. 0x3f800000)"))
+}
+
+/// step to the next representable f32 above the input (towards +inf)
+///
+/// positive floats are ordered the same way as their bit-patterns,
+/// so stepping up is a matter of incrementing the bits (or decrementing for negatives).
+pub fn next_up(x: f32) -> f32 {
+    // nothing lies beyond +inf, and NaN has no neighbours
+    if x.is_nan() || x == f32::INFINITY {
+        return x;
+    }
+    // both +0.0 and -0.0 step up to the smallest positive subnormal
+    if x == 0.0 {
+        return f32::from_bits(1);
+    }
+
+    let bits = x.to_bits();
+    if x > 0.0 {
+        f32::from_bits(bits + 1)
+    } else {
+        f32::from_bits(bits - 1)
+    }
+}
+
+/// step to the next representable f32 below the input (towards -inf)
+pub fn next_down(x: f32) -> f32 {
+    // mirror image of stepping up
+    -next_up(-x)
+}
+
+#[test]
+pub fn test_next_up_is_one_ulp_away() {
+    // the ULP at 1.0 is 2^-23 (aka machine epsilon)
+    assert_eq!(next_up(1.0) - 1.0, f32::EPSILON);
+    assert_eq!(next_up(1.0).to_bits(), 1.0_f32.to_bits() + 1);
+    assert_eq!(next_up(-1.0).to_bits(), (-1.0_f32).to_bits() - 1);
+}
+
+#[test]
+pub fn test_next_down_crosses_zero() {
+    // stepping below zero yields the smallest negative subnormal
+    let smallest_neg_subnormal = f32::from_bits(0x8000_0001);
+    assert_eq!(next_down(0.0).to_bits(), smallest_neg_subnormal.to_bits());
+    assert_eq!(
+        next_up(smallest_neg_subnormal).to_bits(),
+        (-0.0_f32).to_bits()
+    );
+}
+
+#[test]
+pub fn test_next_up_and_down_at_infinities() {
+    assert_eq!(next_up(f32::INFINITY), f32::INFINITY);
+    assert_eq!(next_down(f32::NEG_INFINITY), f32::NEG_INFINITY);
+    assert_eq!(next_up(f32::MAX), f32::INFINITY);
+    assert_eq!(next_down(f32::INFINITY), f32::MAX);
+    assert!(next_up(f32::NAN).is_nan());
+}
+
+#[test]
+pub fn test_compare_adjacent_floats() {
+    let a = 1.0_f32;
+    let b = next_up(a);
+    let diff = DeconstructedFloat32::new(a).diff(&DeconstructedFloat32::new(b));
+
+    // neighbouring floats only differ by the last mantissa bit
+    assert_eq!(diff.sign, 0);
+    assert_eq!(diff.exponent, 0);
+    assert_eq!(diff.mantissa.count_ones(), 1);
+    assert_eq!(diff.mantissa, 0b1);
+}
+
+#[test]
+pub fn test_deconstruct_components() {
+    let one = DeconstructedFloat32::new(1.0);
+    assert_eq!(one.sign_bit, 0);
+    assert_eq!(one.exponent_byte, 127);
+    assert_eq!(one.mantissa_bytes, [0, 0, 0]);
+
+    // -2.5 = -1.25 * 2^1
+    let neg = DeconstructedFloat32::new(-2.5);
+    assert_eq!(neg.sign_bit, 1);
+    assert_eq!(neg.exponent_byte, 128);
+    assert_eq!(neg.mantissa_bytes, [0b0100000, 0, 0]);
+}
+
+#[test]
+pub fn test_deconstructed_float_is_static() {
+    fn assert_static<T: 'static>(_: T) {}
+
+    // no borrow of the input is held, so the deconstruction can outlive it
+    let deconstructed = {
+        let val = 3.0_f32;
+        DeconstructedFloat32::new(val)
+    };
+    assert_eq!(deconstructed.float, 3.0);
+    assert_static(deconstructed);
+}
+
+#[test]
+pub fn test_parse_bits() {
+    let one = DeconstructedFloat32::new(f32::from_bits(parse_bits("0x3f800000").unwrap()));
+    assert_eq!(one.float, 1.0);
+    assert_eq!(one.exponent_byte, 127);
+    assert_eq!(parse_bits("3F800000"), Ok(0x3f800000));
+
+    assert!(parse_bits("0x").is_err());
+    assert!(parse_bits("0xZZ").is_err());
+    assert!(parse_bits("0x1ffffffff").is_err());
+}
+
+#[test]
+pub fn test_parse_hex_float() {
+    // 0x1.8p1 = 1.5 * 2^1
+    assert_eq!(parse_f32("0x1.8p1"), Ok(3.0));
+    assert_eq!(parse_f32("-0x1p-2"), Ok(-0.25));
+    assert_eq!(parse_f32("0X.4P0"), Ok(0.25));
+    assert_eq!(parse_f32("0x10"), Ok(16.0));
+    assert_eq!(parse_f32("2.5"), Ok(2.5));
+
+    assert!(parse_f32("0x1.8q1").is_err());
+    assert!(parse_f32("0x1.8p").is_err());
+    assert!(parse_f32("0xp1").is_err());
+    assert!(parse_f32("one").is_err());
+
+    // signs only come before the prefix
+    for input in ["0x+1", "0x-1", "0x1.+8", "0x+.8p1"] {
+        assert!(parse_f32(input).is_err(), "{input}");
+    }
+    // huge exponents saturate (to zero or infinity) rather than overflowing
+    assert_eq!(parse_f32("0x1.8p-2147483648"), Ok(0.0));
+    assert_eq!(parse_f64("-0x1.8p-2147483648"), Ok(-0.0));
+    assert_eq!(parse_f64("0x1p2147483647"), Ok(f64::INFINITY));
+    assert_eq!(parse_f64("0x0p2147483647"), Ok(0.0));
+    // ...and tiny ones still reach the subnormals
+    assert_eq!(parse_f64("0x1p-1074"), Ok(f64::from_bits(1)));
+    assert_eq!(parse_f64("0x0.0000000000001p-1022"), Ok(f64::from_bits(1)));
+}
+
+#[test]
+pub fn test_markdown_report() {
+    let report = DeconstructedFloat32::new(1.0).to_markdown();
+
+    assert!(report.contains("| --- | --- |"));
+    assert!(report.contains("| input (bits) | `00111111100000000000000000000000` |"));
+    assert!(report.contains("| sign | `0` |"));
+    assert!(report.contains("| exponent | `01111111` |"));
+    assert!(report.contains("| mantissa | `00000000000000000000000` |"));
+}
+
+#[test]
+pub fn test_value_and_classify() {
+    for val in [
+        1.0,
+        -2.5,
+        0.1,
+        f32::MAX,
+        f32::MIN_POSITIVE,
+        1e-40,
+        0.0,
+        -0.0,
+    ] {
+        let deconstructed = DeconstructedFloat32::new(val);
+        assert_eq!(deconstructed.value().to_bits(), val.to_bits());
+        assert_eq!(deconstructed.classify(), val.classify());
+    }
+    assert_eq!(
+        DeconstructedFloat32::new(f32::NEG_INFINITY).value(),
+        f32::NEG_INFINITY
+    );
+    assert!(DeconstructedFloat32::new(f32::NAN).value().is_nan());
+    assert_eq!(DeconstructedFloat32::new(1e-40).class_name(), "subnormal");
+}
+
+#[test]
+pub fn test_kv() {
+    assert_eq!(
+        DeconstructedFloat32::new(-2.5).to_kv(),
+        "input=-2.5\nsign=1\nexponent=128\nmantissa=2097152\nvalue=-2.5\nclass=normal\n"
+    );
+}
+
+#[test]
+pub fn test_csv_rows() {
+    let csv: String = [1.0, -2.5]
+        .map(|v| DeconstructedFloat32::new(v).to_csv_row())
+        .concat();
+    let csv = format!("{CSV_HEADER}{csv}");
+    let lines: Vec<&str> = csv.lines().collect();
+
+    assert_eq!(lines[0], "input,sign,exponent,mantissa,value,class");
+    assert_eq!(lines[1], "1.0,0,127,0,1.0,normal");
+    assert_eq!(lines[2], "-2.5,1,128,2097152,-2.5,normal");
+    assert_eq!(lines.len(), 3);
+}
+
+#[test]
+pub fn test_is_power_of_two() {
+    for val in [1.0, 0.5, 8.0, -8.0, f32::MIN_POSITIVE, f32::from_bits(1)] {
+        assert!(DeconstructedFloat32::new(val).is_power_of_two(), "{val}");
+    }
+    for val in [3.0, 0.0, f32::INFINITY, f32::NAN, f32::from_bits(3)] {
+        assert!(!DeconstructedFloat32::new(val).is_power_of_two(), "{val}");
+    }
+    assert!(
+        DeconstructedFloat32::new(-8.0)
+            .to_report()
+            .contains("= -2^3 exactly")
+    );
+    assert!(
+        !DeconstructedFloat32::new(3.0)
+            .to_report()
+            .contains("exactly")
+    );
+}
+
+#[test]
+pub fn test_significant_bits() {
+    let bits = |v: f32| DeconstructedFloat32::new(v).significant_bits();
+
+    assert_eq!(bits(1.0), 1);
+    assert_eq!(bits(1.5), 2);
+    assert_eq!(bits(-3.0), 2);
+    assert_eq!(bits(f32::from_bits(0x3fff_ffff)), 24); // full mantissa
+    assert_eq!(bits(f32::from_bits(0b101)), 3); // subnormal
+    assert_eq!(bits(0.0), 0);
+    assert_eq!(bits(f32::INFINITY), 0);
+}
+
+#[test]
+pub fn test_to_fixed() {
+    let q8_8 = |v: f32| DeconstructedFloat32::new(v).to_fixed(8, 8);
+
+    assert_eq!(q8_8(1.5), Ok(0x0180));
+    assert_eq!(q8_8(-1.5), Ok(-0x0180));
+    assert_eq!(q8_8(0.00390625), Ok(1)); // 2^-8 is the smallest step
+    assert_eq!(q8_8(1.0 / 3.0), Ok(85)); // rounds to the nearest step
+
+    // Q8.8 spans [-128, 128 - 2^-8]
+    assert_eq!(q8_8(128.0 - 1.0 / 256.0), Ok(0x7FFF));
+    assert_eq!(q8_8(300.0), Err(FixedError::Saturated(0x7FFF)));
+    assert_eq!(q8_8(-300.0), Err(FixedError::Saturated(-0x8000)));
+    assert_eq!(q8_8(f32::INFINITY), Err(FixedError::Saturated(0x7FFF)));
+    assert_eq!(q8_8(f32::NAN), Err(FixedError::Saturated(0)));
+
+    // the width has to be 1 to 63 bits, without overflowing
+    let one = DeconstructedFloat32::new(1.0);
+    assert_eq!(one.to_fixed(1, 62), Ok(1 << 62));
+    for (int_bits, frac_bits) in [
+        (0, 0),
+        (32, 32),
+        (u32::MAX, 0),
+        (u32::MAX, 1),
+        (1, u32::MAX),
+    ] {
+        assert_eq!(
+            one.to_fixed(int_bits, frac_bits),
+            Err(FixedError::InvalidWidth {
+                int_bits,
+                frac_bits
+            })
+        );
+    }
+}
+
+#[test]
+pub fn test_round_trip_bits() {
+    // compare bit-patterns rather than values so NaN payloads are also checked
+    let round_trip = |bits: u32| {
+        DeconstructedFloat32::new(f32::from_bits(bits))
+            .to_f32()
+            .to_bits()
+    };
+
+    // all-zero, all-one, and sign-bit-only patterns
+    for bits in [0x0000_0000, 0xFFFF_FFFF, 0x8000_0000] {
+        assert_eq!(round_trip(bits), bits, "{bits:#010x}");
+    }
+
+    // sweep a large set of pseudo-random patterns (xorshift keeps this deterministic)
+    let mut bits: u32 = 0x2545_F491;
+    for _ in 0..100_000 {
+        bits ^= bits << 13;
+        bits ^= bits >> 17;
+        bits ^= bits << 5;
+        assert_eq!(round_trip(bits), bits, "{bits:#010x}");
+    }
+}
+
+#[test]
+pub fn test_from_bytes_endianness() {
+    // 1.5 is encoded as 0x3FC00000
+    let be = DeconstructedFloat32::from_bytes(&[0x3F, 0xC0, 0x00, 0x00], Endianness::Big);
+    let le = DeconstructedFloat32::from_bytes(&[0x00, 0x00, 0xC0, 0x3F], Endianness::Little);
+
+    assert_eq!(be.float, 1.5);
+    assert_eq!(le.float, 1.5);
+    assert_eq!(be.sign_bit, le.sign_bit);
+    assert_eq!(be.exponent_byte, le.exponent_byte);
+    assert_eq!(be.mantissa_bytes, le.mantissa_bytes);
+}
+
+#[test]
+pub fn test_deconstruct_f64() {
+    let one = DeconstructedFloat64::new(1.0);
+    assert_eq!((one.sign_bit, one.exponent, one.mantissa), (0, 1023, 0));
+
+    // 0.1 = 1.6 * 2^-4, and 0.6 in binary is 0.1001 1001 1001... (rounded up at the end)
+    let tenth = DeconstructedFloat64::new(0.1);
+    assert_eq!(tenth.exponent, 1023 - 4);
+    assert_eq!(tenth.mantissa, 0x999999999999A);
+
+    for val in [0.1, -2.5, f64::MIN_POSITIVE / 2.0, f64::INFINITY, -0.0] {
+        assert_eq!(
+            DeconstructedFloat64::new(val).to_f64().to_bits(),
+            val.to_bits()
+        );
+    }
+}
+
+#[test]
+pub fn test_parse_f64() {
+    assert_eq!(parse_f64("0.1"), Ok(0.1));
+    assert_eq!(parse_f64("-0x1.8p1"), Ok(-3.0));
+    // more hex digits than an f32 could hold, but not an f64
+    assert_eq!(parse_f64("0x1.0000000001p0"), Ok(1.0 + 2f64.powi(-40)));
+    // the C99 form of 0.1 fills an f64's 53-bit significand exactly
+    assert_eq!(parse_f64("0x1.999999999999ap-4"), Ok(0.1));
+    assert_eq!(parse_f32("0x1.99999ap-4"), Ok(0.1));
+    // only significant digits count towards the 16 a u64 holds
+    assert_eq!(parse_f64("0x0000000000000001.8000000000000000p1"), Ok(3.0));
+    assert_eq!(parse_f64("0x1.000000000000001p0"), Ok(1.0));
+    assert!(parse_f64("0x1.0000000000000001p0").is_err());
+    assert!(parse_f64("1e400").unwrap().is_infinite());
+    assert!(parse_f64("nope").is_err());
+}
+
+#[test]
+pub fn test_precision() {
+    let pi = DeconstructedFloat32::new(std::f32::consts::PI);
+    assert_eq!(pi.decimal(DEFAULT_PRECISION), "3.14159274");
+    assert_eq!(pi.scientific(DEFAULT_PRECISION), "3.14159274e0");
+    assert_eq!(pi.decimal(3), "3.14");
+    assert_eq!(pi.scientific(3), "3.14e0");
+
+    let tenth = DeconstructedFloat32::new(0.1);
+    assert_eq!(tenth.decimal(DEFAULT_PRECISION), "0.100000001");
+    assert_eq!(tenth.scientific(2), "1.0e-1");
+
+    // rounding up carries into the next digit
+    assert_eq!(DeconstructedFloat32::new(9.99).decimal(2), "10");
+    assert_eq!(DeconstructedFloat32::new(123456.0).decimal(3), "123456");
+    assert_eq!(DeconstructedFloat32::new(f32::INFINITY).decimal(3), "inf");
+
+    // the default table shows enough digits to round-trip
+    assert!(
+        tenth
+            .to_report()
+            .contains("Value: 0.100000001 (1.00000001e-1)")
+    );
+}
+
+#[test]
+pub fn test_exact_rational() {
+    let rational = |v: f32| DeconstructedFloat32::new(v).exact_rational();
+
+    assert_eq!(rational(0.5), Some((1, 2)));
+    assert_eq!(rational(0.75), Some((3, 4)));
+    assert_eq!(rational(3.0), Some((3, 1)));
+    assert_eq!(rational(-1.5), Some((-3, 2)));
+    assert_eq!(rational(0.0), Some((0, 1)));
+    assert_eq!(rational(0.1), Some((13421773, 134217728)));
+    assert_eq!(rational(2f32.powi(62)), Some((1 << 62, 1)));
+
+    // too large or small for an i64, or not a number at all
+    assert_eq!(rational(2f32.powi(63)), None);
+    assert_eq!(rational(f32::MIN_POSITIVE), None);
+    assert_eq!(rational(f32::INFINITY), None);
+    assert_eq!(rational(f32::NAN), None);
+
+    assert!(
+        DeconstructedFloat32::new(0.75)
+            .to_report()
+            .contains("Exact: 3/4\n")
+    );
+}
+
+#[test]
+pub fn test_representation_error() {
+    let (absolute, relative) = DeconstructedFloat32::new(0.1).representation_error(0.1);
+    assert!(absolute > 0.0 && absolute < 1e-8, "{absolute}");
+    assert!(relative > 0.0 && relative < 1e-7, "{relative}");
+
+    assert_eq!(
+        DeconstructedFloat32::new(0.5).representation_error(0.5),
+        (0.0, 0.0)
+    );
+    assert_eq!(
+        DeconstructedFloat32::new(0.0).representation_error(0.0),
+        (0.0, 0.0)
+    );
+}
+
+#[test]
+pub fn test_exact_decimal() {
+    let exact = |v: f32| DeconstructedFloat32::new(v).exact_decimal();
+
+    assert_eq!(exact(0.1), "0.100000001490116119384765625");
+    assert_eq!(exact(-0.75), "-0.75");
+    assert_eq!(exact(3.0), "3");
+    assert_eq!(exact(0.0), "0");
+    assert_eq!(exact(-0.0), "-0");
+    assert_eq!(exact(f32::INFINITY), "inf");
+    assert_eq!(exact(f32::MAX), "340282346638528859811704183484516925440");
+
+    // the smallest subnormal is 2^-149, so has exactly 149 decimal places
+    let tiny = exact(f32::from_bits(1));
+    assert!(tiny.starts_with("0.000000000000000000000000000000000000000000001401298"));
+    assert_eq!(tiny.split_once('.').unwrap().1.len(), 149);
+}
+
+#[test]
+pub fn test_from_parts() {
+    let one = DeconstructedFloat32::from_parts(0, 127, [0, 0, 0]).unwrap();
+    assert_eq!(one.to_f32(), 1.0);
+    assert_eq!(one.value(), 1.0);
+
+    let tenth = DeconstructedFloat32::from_parts(0, 123, [0x4C, 0xCC, 0xCD]).unwrap();
+    assert_eq!(tenth.to_f32(), 0.1);
+    assert!(tenth.to_report().contains("Input: 0.1\n"));
+
+    assert!(
+        DeconstructedFloat32::from_parts(1, 0xFF, [0, 0, 0])
+            .unwrap()
+            .to_f32()
+            .is_infinite()
+    );
+}
+
+#[test]
+pub fn test_from_parts_rejects_bad_parts() {
+    let error = |sign, exponent, mantissa| {
+        DeconstructedFloat32::from_parts(sign, exponent, mantissa)
+            .err()
+            .unwrap()
+    };
+    assert_eq!(
+        error(0, 127, [0x80, 0, 0]),
+        "Mantissa 800000 must fit in 23 bits (its top byte at most 7F)"
+    );
+    assert_eq!(error(2, 127, [0, 0, 0]), "Sign bit 2 must be 0 or 1");
+}
+
+#[test]
+pub fn test_bits() {
+    assert_eq!(DeconstructedFloat32::new(1.0).bits(), 1.0_f32.to_bits());
+    assert_eq!(DeconstructedFloat32::new(-0.0).bits(), 0x8000_0000);
+
+    let bits = 0x7FC0_0001;
+    assert_eq!(DeconstructedFloat32::new(f32::from_bits(bits)).bits(), bits);
+}
+
+#[test]
+pub fn test_is_integer() {
+    let is_integer = |v: f32| DeconstructedFloat32::new(v).is_integer();
+
+    for val in [3.0, -3.0, 0.0, -0.0, 1.0, 16777215.0, 16777216.0, f32::MAX] {
+        assert!(is_integer(val), "{val}");
+    }
+    for val in [
+        3.5,
+        0.1,
+        -0.5,
+        8388607.5,
+        f32::MIN_POSITIVE,
+        f32::INFINITY,
+        f32::NAN,
+    ] {
+        assert!(!is_integer(val), "{val}");
+    }
+}
+
+#[test]
+pub fn test_mantissa_int() {
+    let one = DeconstructedFloat32::new(1.0);
+    assert_eq!(one.mantissa_int(), 0);
+    assert_eq!(one.significand_int(), 0x800000);
+
+    let one_and_a_half = DeconstructedFloat32::new(1.5);
+    assert_eq!(one_and_a_half.mantissa_int(), 0x400000);
+    assert_eq!(one_and_a_half.significand_int(), 0xC00000);
+    assert!(
+        one_and_a_half
+            .to_report()
+            .contains("Mantissa: 4194304 (significand 12582912)\n")
+    );
+
+    // subnormals have no implicit bit
+    let tiny = DeconstructedFloat32::new(f32::from_bits(3));
+    assert_eq!(tiny.mantissa_int(), 3);
+    assert_eq!(tiny.significand_int(), 3);
+}
+
+#[test]
+pub fn test_report_column_widths() {
+    let float = DeconstructedFloat32::new(1.0);
+    assert_eq!(
+        float.to_bit_rows().lines().nth(1),
+        Some("| sign         | 00000000000000000000000000000000 |")
+    );
+
+    let options = ReportOptions {
+        label_width: 16,
+        bits_width: 34,
+        ..ReportOptions::default()
+    };
+    let rows = float.to_bit_rows_with(&options);
+    assert_eq!(
+        rows.lines().collect::<Vec<_>>(),
+        [
+            "| input (bits)     | 00111111100000000000000000000000   |",
+            "| sign             | 00000000000000000000000000000000   |",
+            "| exponent         | 00111111100000000000000000000000   |",
+            "| mantissa         | 00000000000000000000000000000000   |",
+        ]
+    );
+    assert!(float.to_report_with(&options).contains(&rows));
+}
+
+#[test]
+pub fn test_implicit_bit() {
+    let normal = DeconstructedFloat32::new(1.5);
+    assert_eq!(normal.implicit_bit(), Some(1));
+    assert!(normal.to_report().contains(&format!(
+        "Implicit bit: 1 (normal, so the significand is 1.1{})",
+        "0".repeat(22)
+    )));
+
+    let subnormal = DeconstructedFloat32::new(f32::from_bits(1));
+    assert_eq!(subnormal.implicit_bit(), Some(0));
+    assert!(subnormal.to_report().contains(&format!(
+        "Implicit bit: 0 (subnormal, so the significand is 0.{}1)",
+        "0".repeat(22)
+    )));
+
+    assert_eq!(DeconstructedFloat32::new(0.0).implicit_bit(), Some(0));
+    assert_eq!(DeconstructedFloat32::new(f32::NAN).implicit_bit(), None);
+    assert!(
+        !DeconstructedFloat32::new(f32::INFINITY)
+            .to_report()
+            .contains("Implicit bit")
+    );
+}
+
+#[test]
+pub fn test_to_bytes() {
+    let one = DeconstructedFloat32::new(1.0);
+    assert_eq!(one.to_bytes(Endianness::Big), [0x3F, 0x80, 0x00, 0x00]);
+    assert_eq!(one.to_bytes(Endianness::Little), [0x00, 0x00, 0x80, 0x3F]);
+
+    for endianness in [Endianness::Big, Endianness::Little] {
+        let bytes = DeconstructedFloat32::new(-0.1).to_bytes(endianness);
+        assert_eq!(
+            DeconstructedFloat32::from_bytes(&bytes, endianness).to_f32(),
+            -0.1
+        );
+    }
+}
+
+#[test]
+pub fn test_to_json() {
+    assert_eq!(
+        DeconstructedFloat32::new(1.0).to_json(),
+        "{ \"input\": 1.0, \"bits\": 1065353216, \"sign\": 0, \"exponent\": 127, \"mantissa\": 0, \"value\": 1.0, \"class\": \"normal\" }"
+    );
+    assert!(
+        DeconstructedFloat32::new(f32::NEG_INFINITY)
+            .to_json()
+            .contains("\"input\": null, \"bits\": 4286578688, \"sign\": 1")
+    );
+    assert_eq!(
+        DeconstructedFloat64::new(-2.0).to_json(),
+        "{ \"input\": -2.0, \"bits\": 13835058055282163712, \"sign\": 1, \"exponent\": 1024, \"mantissa\": 0 }"
+    );
 }
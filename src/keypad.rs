@@ -0,0 +1,88 @@
+use std::collections::HashMap;
+
+/// The CHIP-8 hex keypad, row by row, as it's physically laid out.
+const PAD_LAYOUT: [u8; 16] = [
+    0x1, 0x2, 0x3, 0xC, //
+    0x4, 0x5, 0x6, 0xD, //
+    0x7, 0x8, 0x9, 0xE, //
+    0xA, 0x0, 0xB, 0xF, //
+];
+
+/// Maps the keys of a host keyboard onto the 16 keys of the CHIP-8 hex keypad.
+///
+/// By default, the 4x4 block `1234/QWER/ASDF/ZXCV` stands in for the keypad.
+/// Host keys are matched case-insensitively.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyBindings {
+    keys: HashMap<char, u8>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        KeyBindings::from_layout("1234qwerasdfzxcv").unwrap()
+    }
+}
+
+impl KeyBindings {
+    /// bind the 16 host keys in `layout` (listed row by row, e.g. "1234azerqsdfwxcv" for AZERTY)
+    /// to the keypad, in the order its keys are laid out
+    pub fn from_layout(layout: &str) -> Result<KeyBindings, String> {
+        let host_keys: Vec<char> = layout.chars().map(|c| c.to_ascii_lowercase()).collect();
+        if host_keys.len() != PAD_LAYOUT.len() {
+            return Err(format!(
+                "'{}' must list exactly {} keys, one per key of the keypad",
+                layout,
+                PAD_LAYOUT.len()
+            ));
+        }
+
+        let keys: HashMap<char, u8> = host_keys.into_iter().zip(PAD_LAYOUT).collect();
+        if keys.len() != PAD_LAYOUT.len() {
+            return Err(format!("'{}' binds the same key more than once", layout));
+        }
+        Ok(KeyBindings { keys })
+    }
+
+    /// bind a host key to a keypad key (0x0 to 0xF), replacing any previous binding of it
+    pub fn bind(&mut self, host: char, key: u8) -> Result<(), String> {
+        if key > 0xF {
+            return Err(format!(
+                "Cannot bind '{}' to key {:#x}: the keypad only has keys 0x0 to 0xF",
+                host, key
+            ));
+        }
+        self.keys.insert(host.to_ascii_lowercase(), key);
+        Ok(())
+    }
+
+    /// the keypad key that a host key is bound to (if any)
+    pub fn key_for(&self, host: char) -> Option<u8> {
+        self.keys.get(&host.to_ascii_lowercase()).copied()
+    }
+}
+
+#[test]
+pub fn test_default_bindings() {
+    let bindings = KeyBindings::default();
+    assert_eq!(bindings.key_for('1'), Some(0x1));
+    assert_eq!(bindings.key_for('4'), Some(0xC));
+    assert_eq!(bindings.key_for('x'), Some(0x0));
+    assert_eq!(bindings.key_for('V'), Some(0xF));
+    assert_eq!(bindings.key_for('p'), None);
+}
+
+#[test]
+pub fn test_custom_bindings() {
+    let mut azerty = KeyBindings::from_layout("1234AZERQSDFWXCV").unwrap();
+    assert_eq!(azerty.key_for('a'), Some(0x4));
+    assert_eq!(azerty.key_for('w'), Some(0xA));
+    assert_eq!(azerty.key_for('q'), Some(0x7));
+
+    azerty.bind('m', 0x7).unwrap();
+    assert_eq!(azerty.key_for('m'), Some(0x7));
+    assert!(azerty.bind('n', 0x10).is_err());
+    assert_eq!(azerty.key_for('n'), None);
+
+    assert!(KeyBindings::from_layout("1234").is_err());
+    assert!(KeyBindings::from_layout("1111qwerasdfzxcv").is_err());
+}
@@ -1,2 +1,7 @@
+pub mod asm;
+pub mod bytes;
 pub mod cpu;
+pub mod disasm;
 pub mod float;
+pub mod keypad;
+pub mod opcode;
@@ -1,11 +1,28 @@
 #![allow(unused_variables, dead_code)]
 
 use colored::Colorize;
-use std::{f32, process::exit};
+use std::{
+    error::Error,
+    f32, fmt,
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Read, Write},
+    ops::Range,
+    path::{Path, PathBuf},
+    process::exit,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum, builder::RangedU64ValueParser};
 
-use sink::{cpu::CPU, float::DeconstructedFloat32};
+use sink::{
+    asm::parse_register,
+    cpu::{CPU, Chip8Error, StepOutcome, TraceHook},
+    disasm::disassemble,
+    float::{
+        CSV_HEADER, DEFAULT_PRECISION, DeconstructedFloat32, DeconstructedFloat64, Endianness,
+        ReportOptions, parse_bits, parse_f32, parse_f64,
+    },
+};
 
 /// Let's sink down into the dingy depths of the OS!
 #[derive(Parser)]
@@ -13,6 +30,26 @@ use sink::{cpu::CPU, float::DeconstructedFloat32};
 struct Args {
     #[command(subcommand)]
     cmd: Commands,
+
+    /// when to color the output ("auto" colors it when writing to a terminal)
+    #[arg(long, global = true, value_enum, default_value_t = ColorMode::Auto)]
+    color: ColorMode,
+
+    /// print the results as JSON instead of text (the final registers, or the float's fields);
+    /// anything else a run prints, such as its trace, goes to stderr instead
+    #[arg(long, global = true)]
+    json: bool,
+}
+
+/// Choices for when to color the output
+#[derive(Clone, Copy, ValueEnum)]
+enum ColorMode {
+    /// color the output when stdout is a terminal (and NO_COLOR isn't set)
+    Auto,
+    /// always color the output, even when piped
+    Always,
+    /// never color the output
+    Never,
 }
 
 #[derive(Subcommand)]
@@ -20,9 +57,18 @@ enum Commands {
     /// Emulate a cpu that's loosely based on the CHIP-8 instruction set
     Cpu {
         /// load the cpu register with data
-        #[arg(short, long, num_args = 1.., value_delimiter = ' ')]
+        #[arg(short, long, num_args = 1.., value_delimiter = ' ', group = "registers")]
         reg: Option<Vec<String>>,
 
+        /// load the cpu register from a file of up to 16 values, in the same form as --reg
+        /// (separated by whitespace, with anything after a '#' ignored)
+        #[arg(long, value_name = "PATH", group = "registers", conflicts_with = "reg")]
+        reg_file: Option<PathBuf>,
+
+        /// interpret the register data as decimal (e.g. 10) rather than hex (e.g. 0A)
+        #[arg(long, requires = "registers")]
+        reg_dec: bool,
+
         /// list of system opcodes for the cpu to execute
         #[arg(short, long, num_args = 1.., value_delimiter = ' ')]
         sys: Vec<String>,
@@ -30,22 +76,351 @@ enum Commands {
         /// list of program opcodes for the cpu to execute
         #[arg(short, long, num_args = 1.., value_delimiter = ' ')]
         prog: Vec<String>,
+
+        /// the program as one contiguous hex string (e.g. 8014800480340000), instead of --prog
+        #[arg(long, value_name = "STRING", conflicts_with = "prog")]
+        prog_hex: Option<String>,
+
+        /// additionally print each instruction as it is executed
+        #[arg(short, long, conflicts_with = "quiet")]
+        verbose: bool,
+
+        /// print each instruction as it is executed, disassembled (e.g. 0x000: 8014  ADD V0, V1)
+        #[arg(long, conflicts_with = "verbose")]
+        trace: bool,
+
+        /// step through the program interactively, one instruction per Enter
+        /// ('c' continues the run, 'q' quits it)
+        #[arg(long)]
+        step: bool,
+
+        /// stop the run after executing at most N instructions (unlimited by default)
+        #[arg(long, value_name = "N", conflicts_with = "step")]
+        max_cycles: Option<usize>,
+
+        /// print nothing but the computed registers
+        #[arg(short, long)]
+        quiet: bool,
+
+        /// print a hex dump of memory after the run
+        #[arg(long)]
+        dump_mem: bool,
+
+        /// restrict the memory dump to a range of hex addresses (e.g. 100:200)
+        #[arg(long, value_name = "START:END", value_parser = parse_mem_range, requires = "dump_mem")]
+        dump_range: Option<Range<usize>>,
+
+        /// print the display after the run
+        #[arg(long)]
+        display: bool,
+
+        /// the characters used to draw lit and unlit pixels (e.g. "#.")
+        #[arg(long, value_name = "ON_OFF", value_parser = parse_pixel_chars, requires = "display")]
+        pixel_chars: Option<(char, char)>,
+
+        /// seed the random numbers drawn by the program, for reproducible runs
+        #[arg(long, conflicts_with = "random")]
+        seed: Option<u64>,
+
+        /// seed the random numbers drawn by the program from the current time
+        #[arg(long)]
+        random: bool,
+
+        /// print the N most frequently executed instructions after the run
+        #[arg(long, value_name = "N")]
+        profile: Option<usize>,
     },
     /// Deconstruct floats into their fixed-point binary representations
     Float {
-        /// floating point number
+        /// floating point number (decimal, or hex-float such as 0x1.8p3)
+        #[arg(required_unless_present_any = ["compare", "bits", "file"], value_parser = parse_float_arg)]
+        number: Option<FloatArg>,
+
+        /// deconstruct the number as a double-precision (64-bit) float
+        #[arg(long, requires = "number", conflicts_with = "format")]
+        f64: bool,
+
+        /// deconstruct the number at both single and double precision, to compare their rounding
+        #[arg(long, requires = "number", conflicts_with_all = ["f64", "format"])]
+        both: bool,
+
+        /// compare two floats, highlighting the bits that differ
+        #[arg(long, num_args = 2, value_names = ["A", "B"], value_parser = parse_f32)]
+        #[arg(conflicts_with_all = ["number", "bits"])]
+        compare: Option<Vec<f32>>,
+
+        /// raw 32-bit pattern to deconstruct (e.g. 0x3f800000)
+        #[arg(long, value_parser = parse_bits, conflicts_with = "number")]
+        bits: Option<u32>,
+
+        /// deconstruct every float in a file, one per line ("-" reads from stdin)
+        #[arg(long, value_name = "PATH", conflicts_with_all = ["number", "compare", "bits"])]
+        file: Option<PathBuf>,
+
+        /// how to render the deconstructed float
+        #[arg(long, value_enum, default_value_t = FloatFormat::Table, conflicts_with = "compare")]
+        format: FloatFormat,
+
+        /// significant digits shown in the float's value (the default is enough to round-trip)
+        #[arg(long, value_name = "N", default_value_t = DEFAULT_PRECISION, conflicts_with = "f64")]
+        #[arg(value_parser = RangedU64ValueParser::<usize>::new().range(1..=MAX_PRECISION))]
+        precision: usize,
+
+        /// print just the table's bit rows (or the csv rows), without the input and value lines
+        #[arg(long, conflicts_with_all = ["compare", "f64", "both"])]
+        no_header: bool,
+
+        /// don't highlight the components in color (also disabled by setting NO_COLOR)
+        #[arg(long)]
+        no_color: bool,
+    },
+    /// Inspect a ROM without running it: a hex dump of each opcode beside its disassembly
+    Mem {
+        /// the ROM to inspect, as loaded into program memory
+        rom: PathBuf,
+    },
+    /// Debug a ROM interactively, with commands read from stdin (try "help")
+    Debug {
+        /// the ROM to debug, as loaded into program memory
+        rom: PathBuf,
+    },
+    /// Disassemble raw opcode bytes, one instruction per line
+    Disasm {
+        /// file of raw bytes to disassemble (stdin if "-" or omitted)
+        path: Option<PathBuf>,
+    },
+    /// Print the bytes of a float as hex, ready to load with `cpu --sys` or `cpu --prog`
+    FloatToBytes {
+        /// floating point number (decimal, or hex-float such as 0x1.8p3)
+        #[arg(value_parser = parse_f32)]
         number: f32,
+
+        /// the order to print the bytes in
+        #[arg(long, value_enum, default_value_t = ByteOrder::Big)]
+        endianness: ByteOrder,
     },
 }
 
+/// Byte orders that can be chosen on the command line
+#[derive(Clone, Copy, ValueEnum)]
+enum ByteOrder {
+    /// most significant byte first
+    Big,
+    /// least significant byte first
+    Little,
+}
+
+impl From<ByteOrder> for Endianness {
+    fn from(order: ByteOrder) -> Endianness {
+        match order {
+            ByteOrder::Big => Endianness::Big,
+            ByteOrder::Little => Endianness::Little,
+        }
+    }
+}
+
+/// the most significant digits `--precision` allows (more than any f32's exact expansion needs)
+const MAX_PRECISION: u64 = 120;
+
+/// A float argument, parsed at both precisions (so each mode gets its own rounding of it)
+#[derive(Clone, Copy)]
+struct FloatArg {
+    single: f32,
+    double: f64,
+}
+
+/// Parse a float argument (decimal, or hex-float) at both single and double precision
+fn parse_float_arg(input: &str) -> Result<FloatArg, String> {
+    Ok(FloatArg {
+        single: parse_f32(input)?,
+        double: parse_f64(input)?,
+    })
+}
+
+/// Output formats supported by the float subcommand
+#[derive(Clone, Copy, ValueEnum)]
+enum FloatFormat {
+    /// terminal table with the components highlighted
+    Table,
+    /// GitHub-flavored markdown table
+    Markdown,
+    /// comma-separated values, one row per float
+    Csv,
+    /// key=value lines, one per component
+    Kv,
+    /// a JSON object per float, one per line (as selected by --json)
+    Json,
+}
+
+/// How the float subcommand renders each deconstructed float
+#[derive(Clone, Copy)]
+struct Rendering {
+    format: FloatFormat,
+    /// layout of the table format
+    report: ReportOptions,
+    /// whether to include the headings and value lines, or only the rows of bits
+    header: bool,
+}
+
+impl Rendering {
+    /// any preamble to emit once, ahead of the rendered floats
+    fn header(&self) -> &'static str {
+        match (self.format, self.header) {
+            (FloatFormat::Csv, true) => CSV_HEADER,
+            _ => "",
+        }
+    }
+
+    fn render(&self, float: &DeconstructedFloat32) -> String {
+        match (self.format, self.header) {
+            (FloatFormat::Table, true) => float.to_report_with(&self.report),
+            (FloatFormat::Table, false) => float.to_bit_rows_with(&self.report),
+            (FloatFormat::Markdown, _) => float.to_markdown(),
+            (FloatFormat::Csv, _) => float.to_csv_row(),
+            (FloatFormat::Kv, _) => float.to_kv(),
+            (FloatFormat::Json, _) => format!("{}\n", float.to_json()),
+        }
+    }
+}
+
 fn main() {
     let args = Args::parse();
 
+    match args.color {
+        // colored already checks for a terminal, and the NO_COLOR/CLICOLOR variables
+        ColorMode::Auto => {}
+        ColorMode::Always => colored::control::set_override(true),
+        ColorMode::Never => colored::control::set_override(false),
+    }
+
+    let json = args.json;
     match args.cmd {
-        Commands::Float { number } => {
+        Commands::Float {
+            number,
+            compare,
+            bits,
+            file,
+            format,
+            f64,
+            both,
+            precision,
+            no_header,
+            no_color,
+        } => {
+            if no_color {
+                colored::control::set_override(false);
+            }
+            let rendering = Rendering {
+                format: if json { FloatFormat::Json } else { format },
+                report: ReportOptions {
+                    precision,
+                    ..ReportOptions::default()
+                },
+                header: !no_header,
+            };
+
+            if let Some(number) = &number
+                && both
+            {
+                if json {
+                    println!(
+                        "{{ \"f32\": {}, \"f64\": {} }}",
+                        DeconstructedFloat32::new(number.single).to_json(),
+                        DeconstructedFloat64::new(number.double).to_json()
+                    );
+                    exit(0);
+                }
+                println!("\nSingle precision (f32):");
+                print!(
+                    "{}",
+                    rendering.render(&DeconstructedFloat32::new(number.single))
+                );
+                println!("Double precision (f64):");
+                DeconstructedFloat64::new(number.double).print();
+                println!(
+                    "Rounding to f32 changes the value by {:e}",
+                    number.single as f64 - number.double
+                );
+                exit(0);
+            }
+
+            // doubles have a far wider range than f32, so aren't range-checked
+            if let Some(number) = &number
+                && f64
+            {
+                let float = DeconstructedFloat64::new(number.double);
+                match json {
+                    true => println!("{}", float.to_json()),
+                    false => float.print(),
+                }
+                exit(0);
+            }
+
+            print!("{}", rendering.header());
+
+            if let Some(path) = file {
+                let all_ok = match path.to_str() {
+                    Some("-") => deconstruct_lines(io::stdin().lock(), rendering),
+                    _ => match File::open(&path) {
+                        Ok(file) => deconstruct_lines(BufReader::new(file), rendering),
+                        Err(err) => {
+                            eprintln!("{}", format!("Cannot read {:?}: {}", path, err).red());
+                            false
+                        }
+                    },
+                };
+                exit(if all_ok { 0 } else { 1 });
+            }
+
+            // any bit-pattern is a valid f32 (even NaN and inf)
+            if let Some(bits) = bits {
+                print!(
+                    "{}",
+                    rendering.render(&DeconstructedFloat32::new(f32::from_bits(bits)))
+                );
+                exit(0);
+            }
+
+            let range = f32::MIN..=f32::MAX;
+
+            // are both numbers within the allowed range?
+            if let Some([a, b]) = compare.as_deref()
+                && range.contains(a)
+                && range.contains(b)
+            {
+                let (a, b) = (DeconstructedFloat32::new(*a), DeconstructedFloat32::new(*b));
+                if json {
+                    let diff = a.diff(&b);
+                    println!(
+                        "{{ \"a\": {}, \"b\": {}, \"differing_bits\": {{ \"sign\": {}, \"exponent\": {}, \"mantissa\": {} }} }}",
+                        a.to_json(),
+                        b.to_json(),
+                        diff.sign.count_ones(),
+                        diff.exponent.count_ones(),
+                        diff.mantissa.count_ones()
+                    );
+                } else {
+                    a.print_comparison(&b);
+                }
+                exit(0);
+            }
+
             // is the number within the allowed range?
-            if (f32::MIN..=f32::MAX).contains(&number) {
-                DeconstructedFloat32::new(&number).print();
+            if let Some(number) = number
+                && range.contains(&number.single)
+            {
+                let float = DeconstructedFloat32::new(number.single);
+                print!("{}", rendering.render(&float));
+                if let (FloatFormat::Table, true) = (rendering.format, rendering.header) {
+                    // the double-precision parse stands in for the decimal that was typed
+                    match float.representation_error(number.double) {
+                        (0.0, _) => println!("Representation error: 0 (exact)\n"),
+                        (absolute, relative) => println!(
+                            "Representation error: {:e} (relative {:e})\n",
+                            absolute, relative
+                        ),
+                    }
+                }
                 exit(0);
             }
 
@@ -54,47 +429,833 @@ fn main() {
                 format!("Must be within range: [{:?}, {:?}]", f32::MIN, f32::MAX).red(),
             );
         }
-        Commands::Cpu { reg, sys, prog } => {
+        Commands::Debug { rom } => {
+            let bytes = std::fs::read(&rom).unwrap_or_else(|err| {
+                exit_with(format!("Cannot read {:?}: {}", rom, err));
+            });
             let mut cpu = CPU::new();
+            cpu.write_prog_mem(&bytes)
+                .unwrap_or_else(|err| exit_with(err));
+            // boot straight into the program
+            let boot = 0x1000 | cpu.program_start() as u16;
+            cpu.write_system_mem(&boot.to_be_bytes())
+                .unwrap_or_else(|err| exit_with(err));
+
+            debug_session(&mut cpu, io::stdin().lock());
+            exit(0);
+        }
+        Commands::Mem { rom } => {
+            let bytes = std::fs::read(&rom).unwrap_or_else(|err| {
+                exit_with(format!("Cannot read {:?}: {}", rom, err));
+            });
+            let mut cpu = CPU::new();
+            cpu.write_prog_mem(&bytes)
+                .unwrap_or_else(|err| exit_with(err));
+
+            let rows = cpu.disassemble_program();
+            for (idx, (addr, _, mnemonic)) in rows.iter().enumerate() {
+                let next = rows
+                    .get(idx + 1)
+                    .map_or(cpu.program_start() + bytes.len(), |row| row.0);
+                let row_bytes = cpu.mem_slice(*addr..next).unwrap_or_default();
+                let hex: Vec<String> = row_bytes
+                    .iter()
+                    .map(|byte| format!("{:02x}", byte))
+                    .collect();
+                println!("{:#05x}: {:<5}  {}", addr, hex.join(" "), mnemonic);
+            }
+            exit(0);
+        }
+        Commands::Disasm { path } => {
+            let mut bytes = Vec::new();
+            let path = path.unwrap_or_else(|| PathBuf::from("-"));
+            let read = match path.to_str() {
+                Some("-") => io::stdin().read_to_end(&mut bytes),
+                _ => File::open(&path).and_then(|mut file| file.read_to_end(&mut bytes)),
+            };
+            read.unwrap_or_else(|err| exit_with(format!("Cannot read {:?}: {}", path, err)));
+
+            for (idx, word) in bytes.chunks(2).enumerate() {
+                match *word {
+                    [hi, lo] => {
+                        let opcode = u16::from_be_bytes([hi, lo]);
+                        println!("{:#05x}: {:04x}  {}", idx * 2, opcode, disassemble(opcode));
+                    }
+                    [byte] => println!("{:#05x}: {:02x}    DB 0x{:02X}", idx * 2, byte, byte),
+                    _ => unreachable!(),
+                }
+            }
+            exit(0);
+        }
+        Commands::FloatToBytes { number, endianness } => {
+            let bytes = DeconstructedFloat32::new(number).to_bytes(endianness.into());
+            let hex: Vec<String> = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+            println!("{}", hex.join(" "));
+            exit(0);
+        }
+        Commands::Cpu {
+            reg,
+            reg_file,
+            reg_dec,
+            sys,
+            prog,
+            prog_hex,
+            verbose,
+            trace,
+            step,
+            max_cycles,
+            quiet,
+            dump_mem,
+            dump_range,
+            profile,
+            display,
+            pixel_chars,
+            seed,
+            random,
+        } => {
+            let seed = match random {
+                true => SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(CPU::DEFAULT_RNG_SEED, |t| t.as_nanos() as u64),
+                false => seed.unwrap_or(CPU::DEFAULT_RNG_SEED),
+            };
+            let mut cpu = CPU::builder().with_rng_seed(seed).build();
+            let chatty = !(json || quiet);
+            if json && step {
+                exit_with("--step is interactive, so can't be used with --json");
+            }
+            // (with --json, stdout only holds the JSON object, so anything else goes to stderr)
+            let mut extra: Box<dyn Write> = match json {
+                true => Box::new(io::stderr()),
+                false => Box::new(io::stdout()),
+            };
 
             // attempt to update the CPU register with the provided values
-            if let Some(reg) = reg {
-                let result = parse_args_to_byte_array(&reg);
+            let reg = match reg_file {
+                Some(path) => Some((
+                    "--reg-file",
+                    read_reg_file(&path).unwrap_or_else(|err| {
+                        exit_with(format!("Cannot read {:?}: {}", path, err))
+                    }),
+                )),
+                None => reg.map(|reg| ("--reg", reg)),
+            };
+            if let Some((arg, reg)) = reg {
+                let result = if reg_dec {
+                    parse_args_to_decimal_bytes(&reg)
+                } else {
+                    parse_args_to_byte_array(&reg)
+                };
+                let result = result.unwrap_or_else(|err| exit_with_arg(arg, err));
                 for (idx, entry) in result.iter().enumerate() {
-                    cpu.reg[idx] = *entry;
+                    cpu.set_reg(u8::try_from(idx).unwrap_or(u8::MAX), *entry)
+                        .unwrap_or_else(|err| exit_with_arg(arg, err));
+                }
+                if chatty {
+                    println!("Loaded register data:\t {:x?}", cpu.reg);
                 }
-                println!("Loaded register data:\t {:x?}", cpu.reg);
             }
 
             // attempt to load opcodes into memory
-            let result = parse_args_to_byte_array(&sys);
-            cpu.write_system_mem(&result);
-            println!("Loaded system memory:\t {:x?}", result);
+            let result =
+                parse_args_to_byte_array(&sys).unwrap_or_else(|err| exit_with_arg("--sys", err));
+            cpu.write_system_mem(&result)
+                .unwrap_or_else(|err| exit_with_arg("--sys", err));
+            if chatty {
+                println!("Loaded system memory:\t {:x?}", result);
+            }
 
-            let result = parse_args_to_byte_array(&prog);
-            cpu.write_prog_mem(&result);
-            println!("Loaded program memory:\t {:x?}", result);
+            let (arg, result) = match prog_hex {
+                Some(hex) => ("--prog-hex", parse_hex_string(&hex)),
+                None => ("--prog", parse_args_to_byte_array(&prog)),
+            };
+            let result = result.unwrap_or_else(|err| exit_with_arg(arg, err));
+            cpu.write_prog_mem(&result)
+                .unwrap_or_else(|err| exit_with_arg(arg, err));
+            if chatty {
+                println!("Loaded program memory:\t {:x?}", result);
+            }
+
+            if verbose || trace {
+                let hook = match json {
+                    true => trace_to(BufWriter::new(io::stderr()), trace),
+                    false => trace_to(BufWriter::new(io::stdout()), trace),
+                };
+                cpu.set_trace_hook(hook);
+            }
 
             // let's go!
-            cpu.run();
-            println!("Computed registers:\t {:x?}", cpu.reg);
+            let result = match (step, max_cycles) {
+                (true, _) => step_interactively(&mut cpu, io::stdin().lock()),
+                (false, Some(max_cycles)) => cpu.run_for(max_cycles).map(|outcome| {
+                    if outcome == StepOutcome::CycleLimit {
+                        eprintln!(
+                            "{}",
+                            format!("Stopped: the cycle limit ({}) was reached", max_cycles)
+                                .yellow()
+                        );
+                    }
+                }),
+                (false, None) => cpu.run().map(|cycles| {
+                    if chatty {
+                        println!("Cycles executed:\t {}", cycles);
+                    }
+                }),
+            };
+            result.unwrap_or_else(|err| exit_with(err));
+            if json {
+                println!("{}", cpu.dump_registers().to_json());
+            } else {
+                println!("Computed registers:\t {:x?}", cpu.reg);
+            }
+
+            // (like the trace, the extras are best-effort)
+            if dump_mem {
+                let dump = cpu.hex_dump(dump_range.unwrap_or(0..usize::MAX));
+                let _ = write!(extra, "{}", dump);
+            }
+
+            if display {
+                let (on, off) = pixel_chars.unwrap_or(('#', ' '));
+                let _ = write!(extra, "{}", cpu.render_ascii_with(on, off));
+            }
+
+            if let Some(top) = profile {
+                for (mnemonic, count) in cpu.profile_report().iter().take(top) {
+                    let _ = writeln!(extra, "{:>8}  {}", count, mnemonic);
+                }
+            }
+            let _ = extra.flush();
+            exit(0);
+        }
+    }
+    exit(1);
+}
+
+/// Deconstruct each float listed in the input (one per line),
+/// skipping blank lines and '#' comments, and reporting any lines that can't be parsed.
+/// Returns false if any line was invalid.
+fn deconstruct_lines(input: impl BufRead, rendering: Rendering) -> bool {
+    let mut all_ok = true;
+    for (idx, line) in input.lines().enumerate() {
+        let line = match line {
+            Ok(line) => line,
+            Err(err) => {
+                eprintln!("{}", format!("Cannot read line {}: {}", idx + 1, err).red());
+                return false;
+            }
+        };
+
+        let entry = line.trim();
+        if entry.is_empty() || entry.starts_with('#') {
+            continue;
+        }
+        match parse_f32(entry) {
+            Ok(number) => print!("{}", rendering.render(&DeconstructedFloat32::new(number))),
+            Err(err) => {
+                eprintln!("{}", format!("line {}: {}", idx + 1, err).red());
+                all_ok = false;
+            }
         }
     }
+    all_ok
+}
+
+/// A trace hook writing each instruction to `out` as it executes (disassembled, if `disassembled`),
+/// flushing every line so a long run shows its progress, and a crash doesn't swallow the last steps.
+fn trace_to(mut out: impl Write + 'static, disassembled: bool) -> TraceHook {
+    Box::new(move |pc, opcode| {
+        let written = match disassembled {
+            true => writeln!(out, "{:#05x}: {:04x}  {}", pc, opcode, disassemble(opcode)),
+            false => writeln!(out, "Executing:\t\t {:#05x}: {:04x}", pc, opcode),
+        };
+        // the trace is best-effort, so e.g. a closed pipe doesn't stop the run
+        let _ = written.and_then(|_| out.flush());
+    })
+}
+
+/// Step through the program one instruction at a time, showing the instruction about to
+/// execute and the registers, and prompting on `input` before each step:
+/// Enter steps, 'c' continues the run, and 'q' (or the end of the input) quits.
+fn step_interactively(cpu: &mut CPU, mut input: impl BufRead) -> Result<(), Chip8Error> {
+    while !cpu.is_halted() {
+        let opcode = cpu.next_opcode()?;
+        println!(
+            "Next:\t\t\t {:#05x}: {:04x}  {}",
+            cpu.dump_registers().pc,
+            opcode,
+            disassemble(opcode)
+        );
+        println!("Registers:\t\t {:x?}", cpu.reg);
+        print!("[Enter] step, [c]ontinue, [q]uit > ");
+        io::stdout().flush().ok();
+
+        let mut line = String::new();
+        if input.read_line(&mut line).unwrap_or(0) == 0 {
+            println!();
+            return Ok(());
+        }
+        match line.trim() {
+            "" => {
+                cpu.step()?;
+            }
+            "c" => return cpu.run().map(|_| ()),
+            "q" => return Ok(()),
+            other => println!("Unknown command '{}'", other),
+        }
+    }
+    Ok(())
+}
+
+/// the most instructions `continue` executes before giving control back, in case of a loop
+const DEBUG_CYCLE_LIMIT: usize = 1_000_000;
+
+/// Run a debugger session on the CPU, reading commands from `input` until 'quit' (or its end):
+///
+/// step [n], continue, break <addr>, regs, mem <addr> [len], disasm [addr], set V<x> <val>, quit
+///
+/// addresses and values are hex, while counts are decimal.
+fn debug_session(cpu: &mut CPU, mut input: impl BufRead) {
+    let show_next = |cpu: &CPU| match cpu.next_opcode() {
+        Ok(opcode) => {
+            let pc = cpu.dump_registers().pc;
+            println!("{:#05x}: {:04x}  {}", pc, opcode, disassemble(opcode));
+        }
+        Err(err) => println!("{}", err.to_string().red()),
+    };
+    show_next(cpu);
+
+    loop {
+        print!("(sink) ");
+        io::stdout().flush().ok();
+
+        let mut line = String::new();
+        if input.read_line(&mut line).unwrap_or(0) == 0 {
+            println!();
+            return;
+        }
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let result = match words[..] {
+            [] => continue,
+            ["quit" | "q"] => return,
+            ["help" | "h"] => {
+                println!(
+                    "Commands: step [n], continue, break <addr>, regs, mem <addr> [len], \
+                     disasm [addr], set V<x> <val>, quit"
+                );
+                Ok(())
+            }
+            ["step" | "s", ref count @ ..] => parse_count(count.first().copied(), 1)
+                .and_then(|count| debug_step(cpu, count))
+                .map(|_| show_next(cpu)),
+            ["continue" | "c"] => cpu
+                .run_for(DEBUG_CYCLE_LIMIT)
+                .map_err(|err| err.to_string())
+                .map(|outcome| {
+                    match outcome {
+                        StepOutcome::Breakpoint => println!("Stopped at a breakpoint"),
+                        StepOutcome::CycleLimit => {
+                            println!("Stopped after {} cycles", DEBUG_CYCLE_LIMIT)
+                        }
+                        _ => {}
+                    }
+                    show_next(cpu)
+                }),
+            ["break" | "b", addr] => parse_hex_addr(addr).map(|addr| {
+                cpu.add_breakpoint(addr);
+                println!("Breakpoint set at {:#05x}", addr);
+            }),
+            ["regs" | "r"] => {
+                let regs = cpu.dump_registers();
+                println!(
+                    "pc: {:#05x}  i: {:#05x}  halted: {}",
+                    regs.pc, regs.i, regs.halted
+                );
+                let reg: Vec<String> = regs.reg.iter().map(|r| format!("{:02x}", r)).collect();
+                println!("V0-VF: {}", reg.join(" "));
+                Ok(())
+            }
+            ["mem" | "m", addr, ref len @ ..] => parse_hex_addr(addr).and_then(|addr| {
+                let len = parse_count(len.first().copied(), 16)?;
+                print!("{}", cpu.hex_dump(addr..addr.saturating_add(len)));
+                Ok(())
+            }),
+            ["disasm" | "d", ref addr @ ..] => {
+                let start = match addr.first() {
+                    Some(addr) => parse_hex_addr(addr),
+                    None => Ok(cpu.dump_registers().pc),
+                };
+                start.map(|start| {
+                    for addr in (start..start.saturating_add(16)).step_by(2) {
+                        let Some(&[hi, lo]) = cpu.mem_slice(addr..addr.saturating_add(2)) else {
+                            break;
+                        };
+                        let opcode = u16::from_be_bytes([hi, lo]);
+                        let marker = if addr == cpu.dump_registers().pc {
+                            "=>"
+                        } else {
+                            "  "
+                        };
+                        println!(
+                            "{} {:#05x}: {:04x}  {}",
+                            marker,
+                            addr,
+                            opcode,
+                            disassemble(opcode)
+                        );
+                    }
+                })
+            }
+            ["set", reg, value] => {
+                parse_register(reg)
+                    .map_err(|err| err.to_string())
+                    .and_then(|reg| match parse_args_to_byte_array(&[value.to_string()]) {
+                        Ok(bytes) if bytes.len() == 1 => {
+                            cpu.set_reg(reg, bytes[0]).map_err(|err| err.to_string())
+                        }
+                        Ok(_) => Err(format!("'{}' is not a single byte", value)),
+                        Err(err) => Err(err.to_string()),
+                    })
+            }
+            _ => Err(format!("Unknown command '{}' (try 'help')", line.trim())),
+        };
+        if let Err(err) = result {
+            println!("{}", err.red());
+        }
+    }
+}
+
+/// Execute up to `count` instructions, stopping early if the CPU halts
+fn debug_step(cpu: &mut CPU, count: usize) -> Result<(), String> {
+    for _ in 0..count {
+        if cpu.step().map_err(|err| err.to_string())? == StepOutcome::Halted {
+            println!("Halted");
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Parse a debugger count argument (decimal), or use the default if it's missing
+fn parse_count(input: Option<&str>, default: usize) -> Result<usize, String> {
+    match input {
+        Some(count) => count
+            .parse()
+            .map_err(|_| format!("'{}' is not a count", count)),
+        None => Ok(default),
+    }
+}
+
+/// Parse a hex address (with or without a "0x" prefix)
+fn parse_hex_addr(input: &str) -> Result<usize, String> {
+    let digits = input
+        .strip_prefix("0x")
+        .or(input.strip_prefix("0X"))
+        .unwrap_or(input);
+    usize::from_str_radix(digits, 16).map_err(|_| format!("'{}' is not a hex address", input))
+}
+
+/// Read the whitespace-separated register values in a file, ignoring '#' comments
+fn read_reg_file(path: &Path) -> io::Result<Vec<String>> {
+    let text = std::fs::read_to_string(path)?;
+    Ok(text
+        .lines()
+        .flat_map(|line| {
+            line.split('#')
+                .next()
+                .unwrap_or_default()
+                .split_whitespace()
+        })
+        .map(str::to_string)
+        .collect())
+}
+
+/// Print an error message and exit with a nonzero status
+fn exit_with(err: impl fmt::Display) -> ! {
+    eprintln!("{}", format!("{}", err).red());
     exit(1);
 }
 
+/// Print an error along with the command-line argument that caused it, and exit
+fn exit_with_arg(arg: &str, err: impl fmt::Display) -> ! {
+    exit_with(format!("Invalid {}: {}", arg, err))
+}
+
+/// Reasons an opcode/register argument could not be parsed into bytes
+#[derive(Debug, PartialEq)]
+enum ParseError {
+    /// the token has an odd number of hex digits, so can't be split into whole bytes
+    OddLength { token: String },
+    /// the token contains a character that isn't a hex digit (at a 1-based position in the token)
+    InvalidHex {
+        token: String,
+        digit: char,
+        position: usize,
+    },
+    /// the token is a bare "0x" prefix, with no digits after it
+    MissingDigits { token: String },
+    /// the token isn't a decimal number that fits in a byte
+    InvalidDecimal { token: String },
+    /// the token isn't made up of whole bytes of binary digits
+    InvalidBinary { token: String },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::OddLength { token } => {
+                write!(f, "Cannot parse '{}': odd number of hex digits", token)
+            }
+            ParseError::InvalidHex {
+                token,
+                digit,
+                position,
+            } => {
+                write!(
+                    f,
+                    "Cannot parse '{}': invalid hex digit '{}' at position {}",
+                    token, digit, position
+                )
+            }
+            ParseError::MissingDigits { token } => {
+                write!(f, "Cannot parse '{}': no digits after the prefix", token)
+            }
+            ParseError::InvalidDecimal { token } => {
+                write!(
+                    f,
+                    "Cannot parse '{}': expected a decimal value from 0-255",
+                    token
+                )
+            }
+            ParseError::InvalidBinary { token } => {
+                write!(
+                    f,
+                    "Cannot parse '{}': expected groups of 8 binary digits",
+                    token
+                )
+            }
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+/// Parse a "start:end" range of hex addresses (with or without a "0x" prefix)
+fn parse_mem_range(input: &str) -> Result<Range<usize>, String> {
+    let err = || format!("'{}' is not a range of hex addresses (e.g. 100:200)", input);
+    let addr = |s: &str| parse_hex_addr(s).map_err(|_| err());
+
+    let (start, end) = input.split_once(':').ok_or_else(err)?;
+    let (start, end) = (addr(start)?, addr(end)?);
+    if start > end {
+        return Err(format!(
+            "Range start {:#x} is beyond its end {:#x}",
+            start, end
+        ));
+    }
+    Ok(start..end)
+}
+
+/// Parse a pair of characters for lit and unlit pixels (e.g. "#.")
+fn parse_pixel_chars(input: &str) -> Result<(char, char), String> {
+    match input.chars().collect::<Vec<char>>()[..] {
+        [on, off] => Ok((on, off)),
+        _ => Err(format!(
+            "'{}' must be exactly two characters: one for lit pixels, then one for unlit",
+            input
+        )),
+    }
+}
+
 /// Iteratively strip two chars from each entry in vector of Strings
 /// until all String entries have been consumed into an array of bytes
-fn parse_args_to_byte_array(input: &Vec<String>) -> Vec<u8> {
+///
+/// Every token must hold whole bytes: an odd number of hex digits (e.g. "ABC") is rejected
+/// with `ParseError::OddLength`, rather than guessing which nibble should be zero-padded.
+/// An optional "0x"/"0X" prefix is stripped from each token, so "0x8014" and "8014" are equivalent.
+/// Tokens prefixed with "0b"/"0B" are instead read as binary, 8 digits per byte.
+fn parse_args_to_byte_array(input: &[String]) -> Result<Vec<u8>, ParseError> {
+    let mut result: Vec<u8> = vec![];
+    for entry in input {
+        result
+            .extend(parse_prefixed_token(entry).unwrap_or_else(|| parse_hex_token(entry, entry))?);
+    }
+    Ok(result)
+}
+
+/// Parse one contiguous string of hex digits (with an optional "0x" prefix) into bytes,
+/// two digits per byte, e.g. "8014800480340000" -> [0x80, 0x14, 0x80, 0x04, ..]
+fn parse_hex_string(input: &str) -> Result<Vec<u8>, ParseError> {
+    let digits = input
+        .strip_prefix("0x")
+        .or(input.strip_prefix("0X"))
+        .unwrap_or(input);
+    if digits.is_empty() {
+        return Err(ParseError::MissingDigits {
+            token: input.to_string(),
+        });
+    }
+    parse_hex_token(input, digits)
+}
+
+/// Parse a token carrying an explicit "0x" (hex) or "0b" (binary) prefix,
+/// returning None if the token has no such prefix.
+fn parse_prefixed_token(entry: &str) -> Option<Result<Vec<u8>, ParseError>> {
+    if let Some(digits) = entry.strip_prefix("0x").or(entry.strip_prefix("0X")) {
+        // a bare prefix holds no bytes
+        if digits.is_empty() {
+            return Some(Err(ParseError::MissingDigits {
+                token: entry.to_string(),
+            }));
+        }
+        return Some(parse_hex_token(entry, digits));
+    }
+    if let Some(digits) = entry.strip_prefix("0b").or(entry.strip_prefix("0B")) {
+        return Some(parse_binary_token(entry, digits));
+    }
+    None
+}
+
+/// Consume pairs of hex digits into bytes (the original entry is kept for error reporting)
+fn parse_hex_token(entry: &str, digits: &str) -> Result<Vec<u8>, ParseError> {
+    // positions count from the start of the entry, including any prefix
+    let offset = entry.chars().count() - digits.chars().count();
+
+    let mut nibbles: Vec<u8> = vec![];
+    for (idx, digit) in digits.chars().enumerate() {
+        // (checking each digit rather than relying on from_str_radix, which permits a '+' sign)
+        let Some(nibble) = digit.to_digit(16) else {
+            return Err(ParseError::InvalidHex {
+                token: entry.to_string(),
+                digit,
+                position: offset + idx + 1,
+            });
+        };
+        nibbles.push(nibble as u8);
+    }
+    if !nibbles.len().is_multiple_of(2) {
+        return Err(ParseError::OddLength {
+            token: entry.to_string(),
+        });
+    }
+    Ok(nibbles
+        .chunks(2)
+        .map(|pair| pair[0] << 4 | pair[1])
+        .collect())
+}
+
+/// Consume groups of 8 binary digits into bytes (the original entry is kept for error reporting)
+fn parse_binary_token(entry: &str, digits: &str) -> Result<Vec<u8>, ParseError> {
+    let err = || ParseError::InvalidBinary {
+        token: entry.to_string(),
+    };
+    if digits.is_empty() || !digits.len().is_multiple_of(8) {
+        return Err(err());
+    }
+    digits
+        .as_bytes()
+        .chunks(8)
+        .map(|byte| {
+            byte.iter().try_fold(0u8, |acc, bit| match bit {
+                b'0' => Ok(acc << 1),
+                b'1' => Ok(acc << 1 | 1),
+                _ => Err(err()),
+            })
+        })
+        .collect()
+}
+
+/// Parse each entry in vector of Strings as a single decimal byte (0-255),
+/// unless the entry is explicitly prefixed as hex ("0x") or binary ("0b")
+fn parse_args_to_decimal_bytes(input: &[String]) -> Result<Vec<u8>, ParseError> {
     let mut result: Vec<u8> = vec![];
     for entry in input {
-        let mut reversed_chars: Vec<char> = entry.chars().rev().collect();
-        while reversed_chars.len() > 0 {
-            let msb = reversed_chars.pop().unwrap();
-            let lsb = reversed_chars.pop().unwrap();
-            let val: String = [msb, lsb].iter().collect();
-            result.push(u8::from_str_radix(&val, 16).unwrap());
+        if let Some(bytes) = parse_prefixed_token(entry) {
+            result.extend(bytes?);
+            continue;
+        }
+        // (only plain digits, as from_str would also accept a '+' sign)
+        let byte = match entry.chars().all(|c| c.is_ascii_digit()) {
+            true => entry.parse::<u8>().ok(),
+            false => None,
+        };
+        result.push(byte.ok_or_else(|| ParseError::InvalidDecimal {
+            token: entry.clone(),
+        })?);
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+fn args(tokens: &[&str]) -> Vec<String> {
+    tokens.iter().map(|t| t.to_string()).collect()
+}
+
+#[test]
+fn test_parse_valid_args() {
+    assert_eq!(
+        parse_args_to_byte_array(&args(&["80", "14", "8024"])),
+        Ok(vec![0x80, 0x14, 0x80, 0x24])
+    );
+    assert_eq!(parse_args_to_byte_array(&args(&["fF"])), Ok(vec![0xFF]));
+}
+
+#[test]
+fn test_parse_hex_prefix() {
+    let expected = Ok(vec![0x80, 0x14]);
+    assert_eq!(parse_args_to_byte_array(&args(&["0x8014"])), expected);
+    assert_eq!(parse_args_to_byte_array(&args(&["8014"])), expected);
+    assert_eq!(parse_args_to_byte_array(&args(&["0X80", "14"])), expected);
+    assert_eq!(parse_args_to_byte_array(&args(&["80", "0x14"])), expected);
+    assert!(parse_args_to_byte_array(&args(&["0x"])).is_err());
+}
+
+#[test]
+fn test_parse_decimal_args() {
+    assert_eq!(
+        parse_args_to_decimal_bytes(&args(&["255", "16"])),
+        Ok(vec![0xFF, 0x10])
+    );
+    for token in ["256", "-1", "+1", "0A", ""] {
+        assert_eq!(
+            parse_args_to_decimal_bytes(&args(&[token])),
+            Err(ParseError::InvalidDecimal {
+                token: token.to_string()
+            })
+        );
+    }
+}
+
+#[test]
+fn test_parse_binary_args() {
+    assert_eq!(
+        parse_args_to_byte_array(&args(&["0b10000000"])),
+        Ok(vec![0x80])
+    );
+    assert_eq!(
+        parse_args_to_byte_array(&args(&["0b1000000000010100", "0x80", "24"])),
+        Ok(vec![0x80, 0x14, 0x80, 0x24])
+    );
+    assert_eq!(
+        parse_args_to_decimal_bytes(&args(&["0b00001010", "10", "0x0A"])),
+        Ok(vec![10, 10, 10])
+    );
+    for token in ["0b100000001", "0b1000000", "0b", "0b1000000 2"] {
+        assert_eq!(
+            parse_args_to_byte_array(&args(&[token])),
+            Err(ParseError::InvalidBinary {
+                token: token.to_string()
+            })
+        );
+    }
+}
+
+#[test]
+fn test_parse_hex_string() {
+    assert_eq!(
+        parse_hex_string("8014800480340000"),
+        parse_args_to_byte_array(&args(&["80", "14", "80", "04", "80", "34", "00", "00"]))
+    );
+    assert_eq!(parse_hex_string("0x00E0"), Ok(vec![0x00, 0xE0]));
+
+    assert_eq!(
+        parse_hex_string("801"),
+        Err(ParseError::OddLength {
+            token: "801".to_string()
+        })
+    );
+    assert!(matches!(
+        parse_hex_string("80 14"),
+        Err(ParseError::InvalidHex { digit: ' ', .. })
+    ));
+    // only hex is accepted, so "0b" is just the byte 0x0B
+    assert_eq!(parse_hex_string("0b10"), Ok(vec![0x0B, 0x10]));
+    assert!(parse_hex_string("").is_err());
+}
+
+#[test]
+fn test_parse_mem_range() {
+    assert_eq!(parse_mem_range("100:110"), Ok(0x100..0x110));
+    assert_eq!(parse_mem_range("0x0:0xFFF"), Ok(0x0..0xFFF));
+    assert!(parse_mem_range("110:100").is_err());
+    assert!(parse_mem_range("100").is_err());
+    assert!(parse_mem_range("10G:200").is_err());
+}
+
+#[test]
+fn test_parse_odd_length_arg() {
+    assert_eq!(
+        parse_args_to_byte_array(&args(&["80", "801"])),
+        Err(ParseError::OddLength {
+            token: "801".to_string()
+        })
+    );
+}
+
+#[test]
+fn test_parse_non_hex_arg() {
+    for (token, digit, position) in [
+        ("8G", 'G', 2),
+        ("+1", '+', 1),
+        ("A8G1", 'G', 3),
+        ("0x80Z0", 'Z', 5),
+    ] {
+        assert_eq!(
+            parse_args_to_byte_array(&args(&["80", token])),
+            Err(ParseError::InvalidHex {
+                token: token.to_string(),
+                digit,
+                position,
+            })
+        );
+    }
+    assert_eq!(
+        parse_args_to_byte_array(&args(&["A8G1"]))
+            .unwrap_err()
+            .to_string(),
+        "Cannot parse 'A8G1': invalid hex digit 'G' at position 3"
+    );
+    assert_eq!(
+        parse_args_to_byte_array(&args(&["0x"])),
+        Err(ParseError::MissingDigits {
+            token: "0x".to_string()
+        })
+    );
+}
+
+#[test]
+fn test_trace_flushes_each_instruction() {
+    use std::{cell::RefCell, rc::Rc};
+
+    /// a writer that only counts text as output once it's flushed
+    #[derive(Clone, Default)]
+    struct FlushLog {
+        pending: Rc<RefCell<Vec<u8>>>,
+        flushed: Rc<RefCell<String>>,
+    }
+
+    impl Write for FlushLog {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.pending.borrow_mut().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            let pending = std::mem::take(&mut *self.pending.borrow_mut());
+            *self.flushed.borrow_mut() += &String::from_utf8(pending).unwrap();
+            Ok(())
         }
     }
-    result
+
+    let log = FlushLog::default();
+    let mut cpu = CPU::new();
+    cpu.write_system_mem(&[0x80, 0x14, 0x80, 0x14, 0x00, 0x00])
+        .unwrap();
+    cpu.set_trace_hook(trace_to(BufWriter::new(log.clone()), true));
+
+    cpu.step().unwrap();
+    assert_eq!(*log.flushed.borrow(), "0x000: 8014  ADD V0, V1\n");
+    cpu.step().unwrap();
+    assert_eq!(log.flushed.borrow().lines().count(), 2);
+    assert!(log.pending.borrow().is_empty());
 }
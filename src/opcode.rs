@@ -0,0 +1,388 @@
+use std::{error::Error, fmt, str::FromStr};
+
+use crate::{
+    asm::{AsmError, parse_line},
+    cpu::{N_MASK, NNN_MASK, OPCODE_GROUP_MASK, Operands, X_MASK, Y_MASK, operands},
+};
+
+/// A CHIP-8 instruction, with its operands (x and y name registers, nnn an address, kk a
+/// byte and n a nibble, as in `Operands`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Opcode {
+    /// 0000: stop executing
+    Halt,
+    /// 00E0: clear the display
+    Clear,
+    /// 00EE: return from a subroutine
+    Return,
+    /// 0NNN: call a machine code routine (ignored)
+    Sys { nnn: u16 },
+    /// 1NNN: jump to nnn
+    Jump { nnn: u16 },
+    /// 2NNN: call the subroutine at nnn
+    Call { nnn: u16 },
+    /// 3XKK: skip the next instruction if Vx == kk
+    SkipEqImmediate { x: u8, kk: u8 },
+    /// 4XKK: skip the next instruction if Vx != kk
+    SkipNeImmediate { x: u8, kk: u8 },
+    /// 5XY0: skip the next instruction if Vx == Vy
+    SkipEqRegister { x: u8, y: u8 },
+    /// 6XKK: Vx = kk
+    LoadImmediate { x: u8, kk: u8 },
+    /// 7XKK: Vx += kk (without setting VF)
+    AddImmediate { x: u8, kk: u8 },
+    /// 8XY0: Vx = Vy
+    AluLoad { x: u8, y: u8 },
+    /// 8XY1: Vx |= Vy
+    AluOr { x: u8, y: u8 },
+    /// 8XY2: Vx &= Vy
+    AluAnd { x: u8, y: u8 },
+    /// 8XY3: Vx ^= Vy
+    AluXor { x: u8, y: u8 },
+    /// 8XY4: Vx += Vy, VF = carry
+    AluAdd { x: u8, y: u8 },
+    /// 8XY5: Vx -= Vy, VF = not borrow
+    AluSub { x: u8, y: u8 },
+    /// 8XY6: Vx >>= 1, VF = the bit shifted out
+    AluShr { x: u8, y: u8 },
+    /// 8XY7: Vx = Vy - Vx, VF = not borrow
+    AluSubn { x: u8, y: u8 },
+    /// 8XYE: Vx <<= 1, VF = the bit shifted out
+    AluShl { x: u8, y: u8 },
+    /// 9XY0: skip the next instruction if Vx != Vy
+    SkipNeRegister { x: u8, y: u8 },
+    /// ANNN: I = nnn
+    LoadIndex { nnn: u16 },
+    /// BNNN: jump to nnn + V0
+    JumpOffset { nnn: u16 },
+    /// CXKK: Vx = a random byte & kk
+    Random { x: u8, kk: u8 },
+    /// DXYN: draw the n-byte sprite at I at (Vx, Vy), VF = collision
+    Draw { x: u8, y: u8, n: u8 },
+    /// EX9E: skip the next instruction if the key Vx is pressed
+    SkipKey { x: u8 },
+    /// EXA1: skip the next instruction if the key Vx isn't pressed
+    SkipNotKey { x: u8 },
+    /// FX07: Vx = the delay timer
+    LoadDelay { x: u8 },
+    /// FX0A: wait for a key press, and store it in Vx
+    WaitKey { x: u8 },
+    /// FX15: the delay timer = Vx
+    SetDelay { x: u8 },
+    /// FX18: the sound timer = Vx
+    SetSound { x: u8 },
+    /// FX1E: I += Vx
+    AddIndex { x: u8 },
+    /// FX29: I = the address of the font sprite for the digit Vx
+    LoadFont { x: u8 },
+    /// FX33: store the decimal digits of Vx at I, I + 1 and I + 2
+    StoreBcd { x: u8 },
+    /// FX55: store V0 to Vx in memory, starting at I
+    StoreRegisters { x: u8 },
+    /// FX65: load V0 to Vx from memory, starting at I
+    LoadRegisters { x: u8 },
+}
+
+/// A word that doesn't encode any CHIP-8 instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidOpcode {
+    pub word: u16,
+}
+
+impl fmt::Display for InvalidOpcode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:#06x} is not a CHIP-8 instruction", self.word)
+    }
+}
+
+impl Error for InvalidOpcode {}
+
+/// Decodes the instruction a 16-bit word encodes, e.g. 0x8014 -> `AluAdd { x: 0, y: 1 }`.
+impl TryFrom<u16> for Opcode {
+    type Error = InvalidOpcode;
+
+    fn try_from(word: u16) -> Result<Opcode, InvalidOpcode> {
+        use Opcode::*;
+
+        let Operands { x, y, n, kk, nnn } = operands(word);
+        let c = (word & OPCODE_GROUP_MASK) >> 12;
+
+        let opcode = match (c, x, y, n) {
+            (0x0, 0x0, 0x0, 0x0) => Halt,
+            (0x0, 0x0, 0xE, 0x0) => Clear,
+            (0x0, 0x0, 0xE, 0xE) => Return,
+            (0x0, _, _, _) => Sys { nnn },
+            (0x1, _, _, _) => Jump { nnn },
+            (0x2, _, _, _) => Call { nnn },
+            (0x3, _, _, _) => SkipEqImmediate { x, kk },
+            (0x4, _, _, _) => SkipNeImmediate { x, kk },
+            (0x5, _, _, 0x0) => SkipEqRegister { x, y },
+            (0x6, _, _, _) => LoadImmediate { x, kk },
+            (0x7, _, _, _) => AddImmediate { x, kk },
+            (0x8, _, _, 0x0) => AluLoad { x, y },
+            (0x8, _, _, 0x1) => AluOr { x, y },
+            (0x8, _, _, 0x2) => AluAnd { x, y },
+            (0x8, _, _, 0x3) => AluXor { x, y },
+            (0x8, _, _, 0x4) => AluAdd { x, y },
+            (0x8, _, _, 0x5) => AluSub { x, y },
+            (0x8, _, _, 0x6) => AluShr { x, y },
+            (0x8, _, _, 0x7) => AluSubn { x, y },
+            (0x8, _, _, 0xE) => AluShl { x, y },
+            (0x9, _, _, 0x0) => SkipNeRegister { x, y },
+            (0xA, _, _, _) => LoadIndex { nnn },
+            (0xB, _, _, _) => JumpOffset { nnn },
+            (0xC, _, _, _) => Random { x, kk },
+            (0xD, _, _, _) => Draw { x, y, n },
+            (0xE, _, 0x9, 0xE) => SkipKey { x },
+            (0xE, _, 0xA, 0x1) => SkipNotKey { x },
+            (0xF, _, 0x0, 0x7) => LoadDelay { x },
+            (0xF, _, 0x0, 0xA) => WaitKey { x },
+            (0xF, _, 0x1, 0x5) => SetDelay { x },
+            (0xF, _, 0x1, 0x8) => SetSound { x },
+            (0xF, _, 0x1, 0xE) => AddIndex { x },
+            (0xF, _, 0x2, 0x9) => LoadFont { x },
+            (0xF, _, 0x3, 0x3) => StoreBcd { x },
+            (0xF, _, 0x5, 0x5) => StoreRegisters { x },
+            (0xF, _, 0x6, 0x5) => LoadRegisters { x },
+            _ => return Err(InvalidOpcode { word }),
+        };
+        Ok(opcode)
+    }
+}
+
+/// Renders the instruction as its mnemonic, e.g. "ADD V3, 0xEE" (the syntax `assemble` reads).
+impl fmt::Display for Opcode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use Opcode::*;
+
+        match *self {
+            Halt => write!(f, "HALT"),
+            Clear => write!(f, "CLS"),
+            Return => write!(f, "RET"),
+            Sys { nnn } => write!(f, "SYS 0x{:03X}", nnn),
+            Jump { nnn } => write!(f, "JP 0x{:03X}", nnn),
+            Call { nnn } => write!(f, "CALL 0x{:03X}", nnn),
+            SkipEqImmediate { x, kk } => write!(f, "SE V{:X}, 0x{:02X}", x, kk),
+            SkipNeImmediate { x, kk } => write!(f, "SNE V{:X}, 0x{:02X}", x, kk),
+            SkipEqRegister { x, y } => write!(f, "SE V{:X}, V{:X}", x, y),
+            LoadImmediate { x, kk } => write!(f, "LD V{:X}, 0x{:02X}", x, kk),
+            AddImmediate { x, kk } => write!(f, "ADD V{:X}, 0x{:02X}", x, kk),
+            AluLoad { x, y } => write!(f, "LD V{:X}, V{:X}", x, y),
+            AluOr { x, y } => write!(f, "OR V{:X}, V{:X}", x, y),
+            AluAnd { x, y } => write!(f, "AND V{:X}, V{:X}", x, y),
+            AluXor { x, y } => write!(f, "XOR V{:X}, V{:X}", x, y),
+            AluAdd { x, y } => write!(f, "ADD V{:X}, V{:X}", x, y),
+            AluSub { x, y } => write!(f, "SUB V{:X}, V{:X}", x, y),
+            AluShr { x, y } => write!(f, "SHR V{:X}, V{:X}", x, y),
+            AluSubn { x, y } => write!(f, "SUBN V{:X}, V{:X}", x, y),
+            AluShl { x, y } => write!(f, "SHL V{:X}, V{:X}", x, y),
+            SkipNeRegister { x, y } => write!(f, "SNE V{:X}, V{:X}", x, y),
+            LoadIndex { nnn } => write!(f, "LD I, 0x{:03X}", nnn),
+            JumpOffset { nnn } => write!(f, "JP V0, 0x{:03X}", nnn),
+            Random { x, kk } => write!(f, "RND V{:X}, 0x{:02X}", x, kk),
+            Draw { x, y, n } => write!(f, "DRW V{:X}, V{:X}, {}", x, y, n),
+            SkipKey { x } => write!(f, "SKP V{:X}", x),
+            SkipNotKey { x } => write!(f, "SKNP V{:X}", x),
+            LoadDelay { x } => write!(f, "LD V{:X}, DT", x),
+            WaitKey { x } => write!(f, "LD V{:X}, K", x),
+            SetDelay { x } => write!(f, "LD DT, V{:X}", x),
+            SetSound { x } => write!(f, "LD ST, V{:X}", x),
+            AddIndex { x } => write!(f, "ADD I, V{:X}", x),
+            LoadFont { x } => write!(f, "LD F, V{:X}", x),
+            StoreBcd { x } => write!(f, "LD B, V{:X}", x),
+            StoreRegisters { x } => write!(f, "LD [I], V{:X}", x),
+            LoadRegisters { x } => write!(f, "LD V{:X}, [I]", x),
+        }
+    }
+}
+
+/// Parses an instruction from its mnemonic, e.g. "JP 0x200" (see `parse_line`).
+impl FromStr for Opcode {
+    type Err = AsmError;
+
+    fn from_str(line: &str) -> Result<Opcode, AsmError> {
+        parse_line(line)
+    }
+}
+
+/// Encodes the instruction as its 16-bit word, e.g. `AluAdd { x: 0, y: 1 }` -> 0x8014.
+/// Operands too wide for their fields are truncated to fit (e.g. nnn to its low 12 bits).
+impl From<Opcode> for u16 {
+    fn from(opcode: Opcode) -> u16 {
+        use Opcode::*;
+
+        let field = |value: u16, mask: u16, shift: u16| (value << shift) & mask;
+        let xy = |c: u16, x: u8, y: u8, n: u16| {
+            c << 12 | field(x as u16, X_MASK, 8) | field(y as u16, Y_MASK, 4) | field(n, N_MASK, 0)
+        };
+        let xkk = |c: u16, x: u8, kk: u8| c << 12 | field(x as u16, X_MASK, 8) | kk as u16;
+        let nnn = |c: u16, nnn: u16| c << 12 | field(nnn, NNN_MASK, 0);
+
+        match opcode {
+            Halt => 0x0000,
+            Clear => 0x00E0,
+            Return => 0x00EE,
+            Sys { nnn: addr } => nnn(0x0, addr),
+            Jump { nnn: addr } => nnn(0x1, addr),
+            Call { nnn: addr } => nnn(0x2, addr),
+            SkipEqImmediate { x, kk } => xkk(0x3, x, kk),
+            SkipNeImmediate { x, kk } => xkk(0x4, x, kk),
+            SkipEqRegister { x, y } => xy(0x5, x, y, 0x0),
+            LoadImmediate { x, kk } => xkk(0x6, x, kk),
+            AddImmediate { x, kk } => xkk(0x7, x, kk),
+            AluLoad { x, y } => xy(0x8, x, y, 0x0),
+            AluOr { x, y } => xy(0x8, x, y, 0x1),
+            AluAnd { x, y } => xy(0x8, x, y, 0x2),
+            AluXor { x, y } => xy(0x8, x, y, 0x3),
+            AluAdd { x, y } => xy(0x8, x, y, 0x4),
+            AluSub { x, y } => xy(0x8, x, y, 0x5),
+            AluShr { x, y } => xy(0x8, x, y, 0x6),
+            AluSubn { x, y } => xy(0x8, x, y, 0x7),
+            AluShl { x, y } => xy(0x8, x, y, 0xE),
+            SkipNeRegister { x, y } => xy(0x9, x, y, 0x0),
+            LoadIndex { nnn: addr } => nnn(0xA, addr),
+            JumpOffset { nnn: addr } => nnn(0xB, addr),
+            Random { x, kk } => xkk(0xC, x, kk),
+            Draw { x, y, n } => xy(0xD, x, y, n as u16),
+            SkipKey { x } => xy(0xE, x, 0x9, 0xE),
+            SkipNotKey { x } => xy(0xE, x, 0xA, 0x1),
+            LoadDelay { x } => xy(0xF, x, 0x0, 0x7),
+            WaitKey { x } => xy(0xF, x, 0x0, 0xA),
+            SetDelay { x } => xy(0xF, x, 0x1, 0x5),
+            SetSound { x } => xy(0xF, x, 0x1, 0x8),
+            AddIndex { x } => xy(0xF, x, 0x1, 0xE),
+            LoadFont { x } => xy(0xF, x, 0x2, 0x9),
+            StoreBcd { x } => xy(0xF, x, 0x3, 0x3),
+            StoreRegisters { x } => xy(0xF, x, 0x5, 0x5),
+            LoadRegisters { x } => xy(0xF, x, 0x6, 0x5),
+        }
+    }
+}
+
+#[test]
+pub fn test_display() {
+    assert_eq!(
+        format!("{}", Opcode::AddImmediate { x: 3, kk: 0xEE }),
+        "ADD V3, 0xEE"
+    );
+    assert_eq!(Opcode::Halt.to_string(), "HALT");
+    assert_eq!(Opcode::Jump { nnn: 0x200 }.to_string(), "JP 0x200");
+    assert_eq!(Opcode::AluAdd { x: 0, y: 1 }.to_string(), "ADD V0, V1");
+    assert_eq!(
+        Opcode::Draw {
+            x: 0xA,
+            y: 0xB,
+            n: 5
+        }
+        .to_string(),
+        "DRW VA, VB, 5"
+    );
+    assert_eq!(Opcode::StoreRegisters { x: 0xF }.to_string(), "LD [I], VF");
+
+    // Debug stays structural
+    assert_eq!(
+        format!("{:?}", Opcode::AddImmediate { x: 3, kk: 0xEE }),
+        "AddImmediate { x: 3, kk: 238 }"
+    );
+}
+
+#[test]
+pub fn test_from_str() {
+    use Opcode::*;
+
+    let forms = [
+        ("HALT", Halt),
+        ("CLS", Clear),
+        ("RET", Return),
+        ("SYS 0x123", Sys { nnn: 0x123 }),
+        ("JP 0x200", Jump { nnn: 0x200 }),
+        ("CALL 0x300", Call { nnn: 0x300 }),
+        ("SE V1, 0x2A", SkipEqImmediate { x: 1, kk: 0x2A }),
+        ("SNE V2, 255", SkipNeImmediate { x: 2, kk: 0xFF }),
+        ("SE V3, V4", SkipEqRegister { x: 3, y: 4 }),
+        ("LD V5, 0b1010", LoadImmediate { x: 5, kk: 0x0A }),
+        ("ADD V3, 0xEE", AddImmediate { x: 3, kk: 0xEE }),
+        ("LD V7, V8", AluLoad { x: 7, y: 8 }),
+        ("OR V9, VA", AluOr { x: 9, y: 0xA }),
+        ("AND VB, VC", AluAnd { x: 0xB, y: 0xC }),
+        ("XOR VD, VE", AluXor { x: 0xD, y: 0xE }),
+        ("ADD V0, V1", AluAdd { x: 0, y: 1 }),
+        ("SUB V1, V2", AluSub { x: 1, y: 2 }),
+        ("SHR V3, V4", AluShr { x: 3, y: 4 }),
+        ("SHR V5", AluShr { x: 5, y: 0 }),
+        ("SUBN V6, V7", AluSubn { x: 6, y: 7 }),
+        ("SHL V8, V9", AluShl { x: 8, y: 9 }),
+        ("SHL VA", AluShl { x: 0xA, y: 0 }),
+        ("SNE VB, VC", SkipNeRegister { x: 0xB, y: 0xC }),
+        ("LD I, 0x400", LoadIndex { nnn: 0x400 }),
+        ("JP V0, 0x500", JumpOffset { nnn: 0x500 }),
+        ("RND VD, 0x0F", Random { x: 0xD, kk: 0x0F }),
+        ("DRW V0, V1, 5", Draw { x: 0, y: 1, n: 5 }),
+        ("SKP V2", SkipKey { x: 2 }),
+        ("SKNP V3", SkipNotKey { x: 3 }),
+        ("LD V4, DT", LoadDelay { x: 4 }),
+        ("LD V5, K", WaitKey { x: 5 }),
+        ("LD DT, V6", SetDelay { x: 6 }),
+        ("LD ST, V7", SetSound { x: 7 }),
+        ("ADD I, V8", AddIndex { x: 8 }),
+        ("LD F, V9", LoadFont { x: 9 }),
+        ("LD B, VA", StoreBcd { x: 0xA }),
+        ("LD [I], VB", StoreRegisters { x: 0xB }),
+        ("ld vc, [i] ; case-insensitive", LoadRegisters { x: 0xC }),
+    ];
+    for (line, opcode) in forms {
+        assert_eq!(line.parse::<Opcode>(), Ok(opcode), "{}", line);
+        // ...and it's the dual of Display
+        assert_eq!(opcode.to_string().parse::<Opcode>(), Ok(opcode), "{}", line);
+    }
+    assert_eq!(u16::from(AluAdd { x: 0, y: 1 }), 0x8014);
+    assert_eq!(u16::from(Jump { nnn: 0x200 }), 0x1200);
+
+    for line in [
+        "",
+        "FOO",
+        "JP",
+        "JP V1, 0x200",
+        "LD V0, 0x100",
+        "LD VG, 1",
+        "DRW V0, V1, 16",
+        "JP label",
+    ] {
+        assert!(line.parse::<Opcode>().is_err(), "{}", line);
+    }
+
+    // errors describe what was expected
+    assert_eq!(
+        "ADD V0".parse::<Opcode>().unwrap_err().to_string(),
+        "Invalid operands in 'ADD V0': expected 'ADD Vx, kk' or 'ADD Vx, Vy' or 'ADD I, Vx'"
+    );
+    assert_eq!(
+        "LD V0, 0x100".parse::<Opcode>().unwrap_err().to_string(),
+        "0x100 is out of range: must be at most 0xff"
+    );
+}
+
+#[test]
+pub fn test_try_from() {
+    assert_eq!(Opcode::try_from(0x8014), Ok(Opcode::AluAdd { x: 0, y: 1 }));
+    assert_eq!(Opcode::try_from(0x0000), Ok(Opcode::Halt));
+    assert_eq!(Opcode::try_from(0x0123), Ok(Opcode::Sys { nnn: 0x123 }));
+    assert_eq!(
+        Opcode::try_from(0xD015),
+        Ok(Opcode::Draw { x: 0, y: 1, n: 5 })
+    );
+    for word in [0x5121, 0x8018, 0x9AB1, 0xE000, 0xF0FF, 0xFFFF] {
+        assert_eq!(Opcode::try_from(word), Err(InvalidOpcode { word }));
+    }
+    assert_eq!(
+        InvalidOpcode { word: 0xFFFF }.to_string(),
+        "0xffff is not a CHIP-8 instruction"
+    );
+
+    // decoding inverts encoding, for every word that's an instruction
+    for word in 0..=u16::MAX {
+        if let Ok(opcode) = Opcode::try_from(word) {
+            assert_eq!(u16::from(opcode), word, "{:#06x}", word);
+        }
+    }
+}
@@ -0,0 +1,777 @@
+use std::{
+    io::Write,
+    process::{Command, Output, Stdio},
+};
+
+/// run the sink binary with the given arguments, feeding it the given stdin
+fn sink(args: &[&str], stdin: impl AsRef<[u8]>) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_sink"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to launch sink");
+
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_ref())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn test_float_batch_from_stdin() {
+    let output = sink(
+        &["float", "--file", "-"],
+        "1.0\n\n# a comment\n-2.5\n0x1.8p1\n",
+    );
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(stdout.matches("Input:").count(), 3);
+    assert!(stdout.contains("Input: -2.5"));
+    assert!(stdout.contains("Input: 3.0"));
+}
+
+#[test]
+fn test_float_batch_reports_bad_lines() {
+    let output = sink(&["float", "--file", "-"], "1.0\nnope\n");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    assert!(!output.status.success());
+    assert_eq!(stdout.matches("Input:").count(), 1);
+    assert!(stderr.contains("line 2"));
+}
+
+#[test]
+fn test_cpu_rejects_odd_length_token() {
+    let output = sink(&["cpu", "--prog", "ABC"], "");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr.contains("Cannot parse 'ABC': odd number of hex digits"));
+    assert!(!stderr.contains("panicked"));
+}
+
+/// pull the values out of a flat JSON array field, e.g. `"registers": [1, 2]`
+fn json_array(json: &str, field: &str) -> Vec<u64> {
+    let start = json
+        .find(&format!("\"{field}\": ["))
+        .expect("missing field")
+        + field.len()
+        + 5;
+    let end = start + json[start..].find(']').unwrap();
+    json[start..end]
+        .split(',')
+        .map(|v| v.trim().parse().unwrap())
+        .collect()
+}
+
+#[test]
+fn test_cpu_json_registers() {
+    let output = sink(
+        &[
+            "cpu", "--reg", "05", "0A", "0A", "--sys", "8014", "8024", "0000", "--json",
+        ],
+        "",
+    );
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(stdout.lines().count(), 1);
+
+    let registers = json_array(&stdout, "registers");
+    assert_eq!(registers.len(), 16);
+    assert_eq!(registers[0], 25);
+    assert!(stdout.contains("\"halted\": true"));
+}
+
+#[test]
+fn test_cpu_quiet_and_verbose() {
+    let program = ["--reg", "05", "0A", "--sys", "8014", "0000"];
+
+    let output = sink(&[&["cpu", "--quiet"], &program[..]].concat(), "");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(output.status.success());
+    assert_eq!(stdout.lines().count(), 1);
+    assert!(stdout.starts_with("Computed registers:"));
+
+    let output = sink(&[&["cpu", "--verbose"], &program[..]].concat(), "");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Loaded system memory:"));
+    assert!(stdout.contains("0x000: 8014"));
+    assert!(stdout.contains("0x002: 0000"));
+
+    let output = sink(
+        &[&["cpu", "--verbose", "--quiet"], &program[..]].concat(),
+        "",
+    );
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8(output.stderr)
+            .unwrap()
+            .contains("cannot be used with")
+    );
+}
+
+#[test]
+fn test_cpu_dump_mem() {
+    // test_call_and_return's program: call the function at 0x100 twice, which adds reg 1 to reg 0 twice
+    let program = [
+        "cpu",
+        "--reg",
+        "05",
+        "0A",
+        "--sys",
+        "2100",
+        "2100",
+        "0000",
+        "--prog",
+        "8014",
+        "8014",
+        "00EE",
+        "--dump-mem",
+    ];
+
+    let output = sink(&program, "");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(output.status.success());
+    assert!(stdout.contains("\n0x100: 80 14 80 14 00 ee 00 00"));
+    assert_eq!(stdout.matches("\n0x").count(), 256);
+
+    let output = sink(
+        &[&program[..], &["--dump-range", "0x100:0x120"]].concat(),
+        "",
+    );
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("\n0x100: 80 14 80 14 00 ee 00 00"));
+    assert_eq!(stdout.matches("\n0x").count(), 2);
+}
+
+#[test]
+fn test_cpu_profile() {
+    let output = sink(
+        &[
+            "cpu",
+            "--reg",
+            "00",
+            "01",
+            "--sys",
+            "8014",
+            "8014",
+            "8014",
+            "0000",
+            "--profile",
+            "1",
+        ],
+        "",
+    );
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    assert!(output.status.success());
+    assert!(stdout.trim_end().ends_with("3  ADD V0, V1"));
+    assert!(!stdout.contains("HALT"));
+}
+
+#[test]
+fn test_cpu_seeded_runs_are_reproducible() {
+    let program = [
+        "cpu", "--sys", "C0FF", "C1FF", "C2FF", "C3FF", "0000", "--json", "--seed",
+    ];
+    let registers = |seed: &str| {
+        let output = sink(&[&program[..], &[seed]].concat(), "");
+        assert!(output.status.success());
+        json_array(&String::from_utf8(output.stdout).unwrap(), "registers")
+    };
+
+    assert_eq!(registers("1234"), registers("1234"));
+    assert_ne!(registers("1234"), registers("4321"));
+}
+
+#[test]
+fn test_cpu_trace() {
+    // test_addition's program: add registers 1, 2 & 3 into register 0
+    let program = [
+        "cpu", "--trace", "--quiet", "--reg", "05", "0A", "0A", "0A", "--sys", "8014", "8024",
+        "8034", "0000",
+    ];
+    let output = sink(&program, "");
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let adds: Vec<&str> = stdout.lines().filter(|l| l.contains("ADD")).collect();
+    assert_eq!(
+        adds,
+        [
+            "0x000: 8014  ADD V0, V1",
+            "0x002: 8024  ADD V0, V2",
+            "0x004: 8034  ADD V0, V3",
+        ]
+    );
+    assert!(stdout.contains("0x006: 0000  HALT"));
+
+    // tracing is off by default
+    let output = sink(&[&program[..1], &program[2..]].concat(), "");
+    assert!(!String::from_utf8(output.stdout).unwrap().contains("ADD"));
+}
+
+#[test]
+fn test_cpu_step() {
+    // test_addition's program: add registers 1, 2 & 3 into register 0
+    let program = [
+        "cpu", "--step", "--quiet", "--reg", "05", "0A", "0A", "0A", "--sys", "8014", "8024",
+        "8034", "0000",
+    ];
+
+    // two steps, then quit: only the first two additions run
+    let output = sink(&program, "\n\nq\n");
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let next: Vec<&str> = stdout.lines().filter(|l| l.contains("Next:")).collect();
+    assert_eq!(next.len(), 3);
+    assert!(next[0].ends_with("0x000: 8014  ADD V0, V1"));
+    assert!(next[1].ends_with("0x002: 8024  ADD V0, V2"));
+    assert!(next[2].ends_with("0x004: 8034  ADD V0, V3"));
+    assert!(stdout.contains("Computed registers:\t [19, a, a, a,"));
+
+    // one step, then continue to the end of the program
+    let output = sink(&program, "\nc\n");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout.lines().filter(|l| l.contains("Next:")).count(), 2);
+    assert!(stdout.contains("Computed registers:\t [23, a, a, a,"));
+}
+
+#[test]
+fn test_cpu_max_cycles() {
+    // jump to self, forever
+    let output = sink(&["cpu", "--sys", "1000", "--max-cycles", "100"], "");
+    assert!(output.status.success());
+    assert!(
+        String::from_utf8(output.stderr)
+            .unwrap()
+            .contains("cycle limit (100) was reached")
+    );
+    assert!(
+        String::from_utf8(output.stdout)
+            .unwrap()
+            .contains("Computed registers:")
+    );
+
+    // programs that finish within the budget don't report it
+    let output = sink(&["cpu", "--sys", "0000", "--max-cycles", "100"], "");
+    assert!(output.status.success());
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn test_cpu_display() {
+    // draw the one-row sprite at 0x006 (0xF0) in the top-left corner
+    let program = [
+        "cpu",
+        "--quiet",
+        "--sys",
+        "A006",
+        "D011",
+        "0000",
+        "F000",
+        "--display",
+    ];
+
+    let output = sink(&program, "");
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let rows: Vec<&str> = stdout.lines().skip(1).collect();
+    assert_eq!(rows.len(), 32);
+    assert_eq!(rows[0], format!("####{}", " ".repeat(60)));
+
+    let output = sink(&[&program[..], &["--pixel-chars", "@."]].concat(), "");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains(&format!("\n@@@@{}\n", ".".repeat(60))));
+    assert!(stdout.contains(&format!("\n{}\n", ".".repeat(64))));
+
+    let output = sink(&[&program[..], &["--pixel-chars", "#"]].concat(), "");
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_cpu_pc_out_of_bounds() {
+    let output = sink(&["cpu", "--sys", "1FFF"], "");
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Program counter 0xfff has run past the end of memory"));
+    assert!(!stderr.contains("panicked"));
+}
+
+#[test]
+fn test_cpu_oversized_system_memory() {
+    let opcodes = vec!["8014"; 129];
+    let output = sink(&[&["cpu", "--sys"], &opcodes[..]].concat(), "");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(
+        String::from_utf8(output.stderr)
+            .unwrap()
+            .contains("Cannot write 258 bytes of system opcodes")
+    );
+}
+
+#[test]
+fn test_cpu_too_many_registers() {
+    let values = vec!["01"; 17];
+    let output = sink(&[&["cpu", "--reg"], &values[..]].concat(), "");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(
+        String::from_utf8(output.stderr)
+            .unwrap()
+            .contains("There is no register 16")
+    );
+}
+
+#[test]
+fn test_cpu_reports_cycles() {
+    let output = sink(&["cpu", "--sys", "8014", "8024", "8034", "0000"], "");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Cycles executed:\t 4\n"));
+
+    let output = sink(&["cpu", "--quiet", "--sys", "8014", "0000"], "");
+    assert!(!String::from_utf8(output.stdout).unwrap().contains("Cycles"));
+}
+
+#[test]
+fn test_float_f64() {
+    let output = sink(&["float", "--f64", "0.1"], "");
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Input: 0.1\n"));
+    // 1 sign bit, 11 exponent bits (1019 = 1023 - 4), then the 52-bit mantissa
+    assert!(stdout.contains(&format!(
+        "| exponent     | 0{}{} |",
+        "01111111011",
+        "0".repeat(52)
+    )));
+    assert!(stdout.contains(&format!(
+        "| mantissa     | {}{} |",
+        "0".repeat(12),
+        "1001".repeat(12) + "1010"
+    )));
+
+    // values beyond the range of an f32 are fine as doubles
+    let output = sink(&["float", "--f64", "1e300"], "");
+    assert!(output.status.success());
+    assert!(
+        String::from_utf8(output.stdout)
+            .unwrap()
+            .contains("Input: 1e300")
+    );
+}
+
+#[test]
+fn test_float_both_precisions() {
+    let output = sink(&["float", "--both", "0.1"], "");
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    let (single, double) = stdout.split_once("Double precision (f64):").unwrap();
+    let mantissa = |report: &str| {
+        let row = report
+            .lines()
+            .find(|l| l.starts_with("| mantissa"))
+            .unwrap();
+        row.split('|')
+            .nth(2)
+            .unwrap()
+            .trim()
+            .trim_start_matches('0')
+            .to_string()
+    };
+    assert!(single.contains("Single precision (f32):"));
+    assert_eq!(mantissa(single), "10011001100110011001101");
+    assert_eq!(mantissa(double), "1001".repeat(12) + "1010");
+    assert!(stdout.contains("Rounding to f32 changes the value by 1.4901161"));
+}
+
+#[test]
+fn test_float_precision() {
+    let table = |args: &[&str]| {
+        let output = sink(args, "");
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let full = table(&["float", "3.14159265"]);
+    let short = table(&["float", "--precision", "3", "3.14159265"]);
+
+    assert!(full.contains("Value: 3.14159274 (3.14159274e0)"));
+    assert!(short.contains("Value: 3.14 (3.14e0)"));
+
+    // only the value line changes; the bits are the same
+    let bits = |report: &str| {
+        report
+            .lines()
+            .filter(|l| l.starts_with('|'))
+            .map(str::to_string)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(bits(&full), bits(&short));
+    assert_eq!(bits(&full).len(), 4);
+}
+
+#[test]
+fn test_float_representation_error() {
+    let output = sink(&["float", "0.1"], "");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let line = stdout
+        .lines()
+        .find(|l| l.starts_with("Representation error:"))
+        .unwrap();
+    assert!(line.starts_with("Representation error: 1.49"), "{line}");
+    assert!(line.contains("(relative 1.49"), "{line}");
+
+    let output = sink(&["float", "0.5"], "");
+    assert!(
+        String::from_utf8(output.stdout)
+            .unwrap()
+            .contains("Representation error: 0 (exact)")
+    );
+}
+
+#[test]
+fn test_float_no_header() {
+    let output = sink(&["float", "--no-header", "--no-color", "1.5"], "");
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        [
+            "| input (bits) | 00111111110000000000000000000000 |",
+            "| sign         | 00000000000000000000000000000000 |",
+            "| exponent     | 00111111100000000000000000000000 |",
+            "| mantissa     | 00000000010000000000000000000000 |",
+        ]
+    );
+
+    // csv rows lose their header too
+    let output = sink(&["float", "--no-header", "--format", "csv", "1.5"], "");
+    assert_eq!(String::from_utf8(output.stdout).unwrap().lines().count(), 1);
+}
+
+#[test]
+fn test_float_kv() {
+    let output = sink(&["float", "--format", "kv", "1.0"], "");
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+    for expected in [
+        "sign=0",
+        "exponent=127",
+        "mantissa=0",
+        "value=1.0",
+        "class=normal",
+    ] {
+        assert!(
+            lines.contains(&expected),
+            "{expected} missing from {lines:?}"
+        );
+    }
+}
+
+#[test]
+fn test_float_to_bytes() {
+    let output = sink(&["float-to-bytes", "1.0"], "");
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "3f 80 00 00\n");
+
+    let output = sink(&["float-to-bytes", "--endianness", "little", "1.0"], "");
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "00 00 80 3f\n");
+}
+
+#[test]
+fn test_mem_inspects_rom() {
+    let rom = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/roms/sprites.ch8");
+    let output = sink(&["mem", rom], "");
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines[0], "0x100: a1 20  LD I, 0x120");
+    assert_eq!(lines[1], "0x102: 60 00  LD V0, 0x00");
+    // the sprite data's odd trailing byte
+    assert_eq!(lines.last(), Some(&"0x124: 90     DB 0x90"));
+}
+
+#[test]
+fn test_mem_reports_oversized_rom() {
+    let path = std::env::temp_dir().join("sink_test_oversized.ch8");
+    std::fs::write(&path, vec![0u8; 8192]).unwrap();
+    let output = sink(&["mem", path.to_str().unwrap()], "");
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr).unwrap().contains("8192"));
+}
+
+#[test]
+fn test_disasm_from_stdin() {
+    for args in [&["disasm", "-"][..], &["disasm"]] {
+        let output = sink(args, [0x80, 0x14]);
+        assert!(output.status.success());
+        assert_eq!(
+            String::from_utf8(output.stdout).unwrap(),
+            "0x000: 8014  ADD V0, V1\n"
+        );
+    }
+}
+
+#[test]
+fn test_color_flag() {
+    let has_ansi = |args: &[&str]| {
+        let output = sink(args, "");
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap().contains('\x1b')
+    };
+    assert!(!has_ansi(&["--color", "never", "float", "1.5"]));
+    assert!(has_ansi(&["--color", "always", "float", "1.5"]));
+    // the flag is global, so can follow the subcommand too
+    assert!(has_ansi(&["float", "1.5", "--color", "always"]));
+    // stdout isn't a terminal here
+    assert!(!has_ansi(&["float", "1.5"]));
+}
+
+/// check that `text` is a single well-formed JSON value (objects, arrays, strings without
+/// escapes, numbers, booleans and null: everything sink emits)
+fn is_json(text: &str) -> bool {
+    fn value(s: &str) -> Option<&str> {
+        let s = s.trim_start();
+        match s.chars().next()? {
+            '{' => {
+                let mut s = s[1..].trim_start();
+                if let Some(rest) = s.strip_prefix('}') {
+                    return Some(rest);
+                }
+                loop {
+                    s = string(s.trim_start())?.trim_start().strip_prefix(':')?;
+                    s = value(s)?.trim_start();
+                    match s.chars().next()? {
+                        ',' => s = &s[1..],
+                        '}' => return Some(&s[1..]),
+                        _ => return None,
+                    }
+                }
+            }
+            '[' => {
+                let mut s = s[1..].trim_start();
+                if let Some(rest) = s.strip_prefix(']') {
+                    return Some(rest);
+                }
+                loop {
+                    s = value(s)?.trim_start();
+                    match s.chars().next()? {
+                        ',' => s = &s[1..],
+                        ']' => return Some(&s[1..]),
+                        _ => return None,
+                    }
+                }
+            }
+            '"' => string(s),
+            _ => {
+                let end = s
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || "+-.".contains(c)))
+                    .unwrap_or(s.len());
+                let token = &s[..end];
+                // (rust would also parse "inf" and "NaN" as floats, but JSON has no such numbers)
+                let number = token
+                    .chars()
+                    .all(|c| c.is_ascii_digit() || "+-.eE".contains(c))
+                    && token.parse::<f64>().is_ok();
+                let ok = ["true", "false", "null"].contains(&token) || number;
+                ok.then_some(&s[end..])
+            }
+        }
+    }
+
+    fn string(s: &str) -> Option<&str> {
+        let rest = s.strip_prefix('"')?;
+        let end = rest.find('"')?;
+        Some(&rest[end + 1..])
+    }
+
+    value(text).is_some_and(|rest| rest.trim().is_empty())
+}
+
+#[test]
+fn test_global_json() {
+    let stdout = |args: &[&str]| {
+        let output = sink(args, "");
+        assert!(output.status.success(), "{args:?}");
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    let cpu = stdout(&["--json", "cpu", "--sys", "8014", "0000"]);
+    assert!(is_json(&cpu), "{cpu}");
+    assert!(cpu.contains("\"pc\": ") && cpu.contains("\"halted\": true"));
+
+    // anything else the run prints goes to stderr, so stdout stays valid JSON
+    let output = sink(
+        &[
+            "--json",
+            "cpu",
+            "--prog",
+            "0000",
+            "--trace",
+            "--dump-mem",
+            "--dump-range",
+            "100:102",
+            "--display",
+            "--profile",
+            "1",
+        ],
+        "",
+    );
+    assert!(output.status.success());
+    let cpu = String::from_utf8(output.stdout).unwrap();
+    assert!(is_json(&cpu), "{cpu}");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("0x000: 0000  HALT"), "{stderr}");
+    assert!(stderr.contains("0x100: 00 00"), "{stderr}");
+    assert!(stderr.contains("1  HALT"), "{stderr}");
+
+    let output = sink(&["--json", "cpu", "--sys", "0000", "--step"], "");
+    assert!(!output.status.success());
+
+    let float = stdout(&["--json", "float", "1.5"]);
+    assert!(is_json(&float), "{float}");
+    assert!(float.contains("\"exponent\": 127") && float.contains("\"class\": \"normal\""));
+
+    // the flag can follow the subcommand, and covers the other float modes
+    for args in [
+        &["float", "--json", "--f64", "0.1"][..],
+        &["float", "--json", "--both", "0.1"],
+        &["float", "--json", "--compare", "1", "2"],
+        &["float", "--json", "--bits", "0x7f800000"],
+    ] {
+        let out = stdout(args);
+        assert!(is_json(&out), "{args:?}: {out}");
+    }
+
+    assert!(!is_json("{ \"a\": 1, }"));
+    assert!(!is_json("{ \"a\": inf }"));
+}
+
+#[test]
+fn test_malformed_args_exit_cleanly() {
+    for (args, message) in [
+        (
+            &["cpu", "--prog", "80G4"][..],
+            "Invalid --prog: Cannot parse '80G4': invalid hex digit 'G' at position 3",
+        ),
+        (
+            &["cpu", "--prog", "0x"],
+            "Invalid --prog: Cannot parse '0x'",
+        ),
+        (
+            &["cpu", "--sys", "0b101"],
+            "Invalid --sys: Cannot parse '0b101'",
+        ),
+        (&["cpu", "--reg", "ZZ"], "Invalid --reg: Cannot parse 'ZZ'"),
+        (
+            &["cpu", "--reg-dec", "--reg", "256"],
+            "Invalid --reg: Cannot parse '256'",
+        ),
+        (&["float", "--precision", "0", "1.0"], "--precision"),
+    ] {
+        let output = sink(args, "");
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(!output.status.success(), "{args:?}");
+        assert!(stderr.contains(message), "{args:?}: {stderr}");
+        assert!(!stderr.contains("panicked"), "{args:?}: {stderr}");
+    }
+}
+
+#[test]
+fn test_prog_hex() {
+    // a program that jumps to program memory, then adds V1 into V0 twice
+    let sys = ["--reg", "01", "02", "--sys", "1100"];
+    let run = |prog: &[&str]| {
+        let output = sink(&[&["cpu", "--json"], &sys[..], prog].concat(), "");
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let contiguous = run(&["--prog-hex", "8014801400000000"]);
+    let delimited = run(&["--prog", "80", "14", "80", "14", "00", "00", "00", "00"]);
+
+    assert_eq!(contiguous, delimited);
+    assert_eq!(json_array(&contiguous, "registers")[0], 5);
+
+    let output = sink(&["cpu", "--prog-hex", "80148"], "");
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8(output.stderr)
+            .unwrap()
+            .contains("Invalid --prog-hex: Cannot parse '80148': odd number of hex digits")
+    );
+}
+
+#[test]
+fn test_reg_file() {
+    let fixture = |name: &str| format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name);
+
+    // ADD V0, V1 runs with the registers loaded from the file
+    let regs = fixture("registers.txt");
+    let output = sink(
+        &[
+            "--json",
+            "cpu",
+            "--reg-file",
+            &regs,
+            "--sys",
+            "8014",
+            "0000",
+        ],
+        "",
+    );
+    assert!(output.status.success());
+    let registers = json_array(&String::from_utf8(output.stdout).unwrap(), "registers");
+    assert_eq!(registers[..4], [15, 10, 0xFF, 0x0F]);
+
+    let too_many = fixture("too_many_registers.txt");
+    let output = sink(&["cpu", "--reg-file", &too_many], "");
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8(output.stderr)
+            .unwrap()
+            .starts_with("Invalid --reg-file: ")
+    );
+
+    let output = sink(&["cpu", "--reg-file", &regs, "--reg", "01"], "");
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_debug_session() {
+    let rom = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/roms/sprites.ch8");
+    // boot, then LD I, 0x120; then run up to the first ADD V0, 0x08
+    let script = "step 2\nregs\nbreak 10a\ncontinue\nset V3 7f\nregs\nquit\n";
+    let output = sink(&["debug", rom], script);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    assert!(stdout.contains("0x102: 6000  LD V0, 0x00"));
+    assert!(stdout.contains("pc: 0x102  i: 0x120  halted: false"));
+    assert!(stdout.contains("Stopped at a breakpoint"));
+    assert!(stdout.contains("pc: 0x10a  i: 0x120  halted: false"));
+    assert!(stdout.contains("V0-VF: 08 00 00 7f 00"));
+
+    // addresses past the end of memory show nothing, rather than overflowing
+    let script = "disasm FFFFFFFFFFFFFFFF
+mem FFFFFFFFFFFFFFFF
+quit
+";
+    let output = sink(&["debug", rom], script);
+    assert!(output.status.success());
+    assert!(
+        !String::from_utf8(output.stderr)
+            .unwrap()
+            .contains("panicked")
+    );
+}
@@ -0,0 +1,58 @@
+//! Runs the bundled test ROMs headless and checks what each one drew.
+//!
+//! ROMs are loaded into program memory (0x100), and started by a jump from system memory.
+//! Each fixture records the hash of the framebuffer a correct run leaves behind (see
+//! `CPU::framebuffer_hash`), so a regression in any opcode the ROM uses changes the hash.
+
+use std::{fs, path::Path};
+
+use sink::cpu::{CPU, StepOutcome};
+
+/// long enough for any of the fixtures to finish
+const MAX_CYCLES: usize = 10_000;
+
+struct Fixture {
+    /// file name, within tests/roms
+    rom: &'static str,
+    /// hash of the framebuffer once the ROM has halted
+    framebuffer_hash: u64,
+}
+
+const FIXTURES: &[Fixture] = &[
+    // tiles a sprite along the top row, then down a row from a subroutine, then sets the timers
+    Fixture {
+        rom: "sprites.ch8",
+        framebuffer_hash: 0xd1401a170cb5f563,
+    },
+];
+
+fn run_rom(name: &str) -> CPU {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/roms")
+        .join(name);
+    let rom = fs::read(&path).unwrap_or_else(|err| panic!("Cannot read {:?}: {}", path, err));
+
+    let mut cpu = CPU::new();
+    cpu.write_system_mem(&[0x11, 0x00]).unwrap(); // JP 0x100
+    cpu.write_prog_mem(&rom).unwrap();
+
+    let outcome = cpu
+        .run_for(MAX_CYCLES)
+        .unwrap_or_else(|err| panic!("{} failed: {}", name, err));
+    assert_eq!(outcome, StepOutcome::Halted, "{} didn't halt", name);
+    cpu
+}
+
+#[test]
+fn test_rom_suite() {
+    for fixture in FIXTURES {
+        let cpu = run_rom(fixture.rom);
+        assert_eq!(
+            cpu.framebuffer_hash(),
+            fixture.framebuffer_hash,
+            "{} drew something unexpected:\n{}",
+            fixture.rom,
+            cpu.render_ascii()
+        );
+    }
+}
@@ -0,0 +1,88 @@
+//! Checks the assembler and disassembler agree on the syntax of every instruction.
+//!
+//! A program is assembled, disassembled, and the disassembly assembled again: the bytes must
+//! come out the same, so each direction accepts exactly what the other produces.
+
+use sink::{asm::assemble, disasm::disassemble};
+
+/// a program using every form of every instruction, in the syntax a person might write it
+const PROGRAM: &str = "
+    ORG 0x200
+    start:
+        CLS
+        SYS 0x123
+        CALL sub
+        SE V1, 0x2A
+        SNE v2, 255
+        SE V3, V4
+        LD V5, 0b1010
+        ADD V6, 1
+        LD V7, V8
+        OR V9, VA
+        AND VB, VC
+        XOR VD, VE
+        ADD VF, V0
+        SUB V1, V2
+        SHR V3, V4
+        SHR V5
+        SUBN V6, V7
+        SHL V8, V9
+        SHL VA
+        SNE VB, VC
+        LD I, sprite
+        JP V0, start
+        RND VD, 0xFF
+        DRW V0, V1, 5
+        SKP V2
+        SKNP V3
+        LD V4, DT
+        LD V5, K
+        LD DT, V6
+        LD ST, V7
+        ADD I, V8
+        LD F, V9
+        LD B, VA
+        LD [I], VB
+        ld vc, [i]
+        JP end
+    sub:
+        RET
+    sprite:
+        SPRITE ########
+        SPRITE #......#
+    end:
+        HALT
+";
+
+/// assemble `source` (at `origin`), and disassemble the result back into source
+fn round_trip(source: &str, origin: u16) -> (Vec<u8>, String) {
+    let assembly = assemble(source).unwrap_or_else(|err| panic!("{}", err));
+    assert_eq!(assembly.origin(), origin);
+
+    let bytes = assembly.bytes().to_vec();
+    let disassembly = bytes
+        .chunks(2)
+        .map(|word| disassemble(u16::from_be_bytes([word[0], word[1]])))
+        .collect::<Vec<_>>()
+        .join("\n");
+    (bytes, format!("ORG {:#05x}\n{}", origin, disassembly))
+}
+
+#[test]
+fn test_assemble_disassemble_round_trip() {
+    let (bytes, disassembly) = round_trip(PROGRAM, 0x200);
+    let (reassembled, redisassembly) = round_trip(&disassembly, 0x200);
+
+    assert_eq!(reassembled, bytes, "disassembly:\n{}", disassembly);
+    // ...and it's a fixed point: the disassembly disassembles to itself
+    assert_eq!(redisassembly, disassembly);
+}
+
+#[test]
+fn test_round_trip_covers_every_opcode_group() {
+    let (bytes, _) = round_trip(PROGRAM, 0x200);
+    let mut groups: Vec<u8> = bytes.chunks(2).map(|word| word[0] >> 4).collect();
+    groups.sort();
+    groups.dedup();
+    assert_eq!(groups, (0x0..=0xF).collect::<Vec<u8>>());
+}